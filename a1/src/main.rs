@@ -1,4 +1,5 @@
 use bevy::{prelude::*, window::*};
+use comp4300_common::cli::{Cli, Flags, MaxFramesPlugin};
 
 // region: Components
 
@@ -23,9 +24,10 @@ struct Rectangle {
 // endregion
 
 fn main() {
+    let cli = Cli::from_env("a1", Flags::default());
     App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
+        .add_plugins((
+            cli.default_plugins(Window {
                 present_mode: PresentMode::AutoNoVsync,
                 mode: WindowMode::Windowed,
                 position: WindowPosition::Automatic,
@@ -44,8 +46,8 @@ fn main() {
                 window_level: WindowLevel::Normal,
                 ..Default::default()
             }),
-            ..Default::default()
-        }))
+            MaxFramesPlugin(cli.max_frames),
+        ))
         .add_systems(Startup, setup)
         .add_systems(
             Update,
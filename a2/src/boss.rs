@@ -0,0 +1,453 @@
+//! Bosses from `BossConfig`: one appears at each milestone, takes many bullets to destroy, fires
+//! bullet-hell patterns that change with its health and bursts into rings of fragments.
+
+// region: includes
+
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, sprite::Anchor};
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use comp4300_common::rng::RandomExt;
+use comp4300_common::telemetry::CollisionCounts;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    BossConfig, BossType, CollisionMode, EnemyConfig, FontConfig, Milestone, Pattern, ScoreConfig,
+};
+use crate::pool::{Pools, TInactive};
+use crate::stats::GameEvent;
+use crate::{
+    collision::is_collision, net, rgb, s_collisions, s_combo, s_enemy_spawner, s_telegraph,
+    safest_point, spawn_popup, spawn_projectile, spawn_small_enemies, AppState, Arena,
+    BoundaryMode, CBoundary, CCollision, CHealth, COwner, CPierce, CScore, CShape, CTelegraph,
+    CTransform, Combo, Players, TBullet, THostile, TPlayer, TimeScale,
+};
+
+// endregion
+
+// region: Boss Structs
+
+/// Where a boss is in its fight.
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct CBoss {
+    /// index into `BossConfig::bosses`
+    pub kind: usize,
+    /// index into the boss type's phases
+    pub phase: usize,
+    /// seconds until the next volley
+    pub cooldown: f32,
+    /// direction in radians rings and spirals start from
+    pub spin: f32,
+}
+
+/// Progress towards the boss milestones of the current game.
+#[derive(Resource, Default, Clone, Serialize, Deserialize, Debug)]
+pub struct BossSchedule {
+    /// enemies spawned, counting towards waves
+    pub spawned: u32,
+    /// indices into `BossConfig::bosses` that already appeared
+    pub fought: Vec<usize>,
+}
+
+/// The health bar at the top of the window of the boss it points to.
+/// The boss a piercing bullet is passing through, which it already hit.
+#[derive(Component)]
+pub struct CStruck(pub Entity);
+
+#[derive(Component)]
+struct TBossBar(Entity);
+
+#[derive(Component)]
+struct TBossBarFill;
+
+// endregion
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossSchedule>().add_systems(
+            FixedUpdate,
+            (
+                s_boss_hits.after(s_collisions).before(s_combo),
+                (s_boss_spawner, s_boss_patterns)
+                    .chain()
+                    .after(s_enemy_spawner)
+                    .before(s_telegraph),
+            )
+                .run_if(resource_exists::<BossConfig>())
+                .run_if(in_state(AppState::InGame))
+                .run_if(net::simulates),
+        );
+    }
+}
+
+/// Draws the health bars, only added with a window.
+pub struct BossBarPlugin;
+
+impl Plugin for BossBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            s_boss_bars
+                .run_if(resource_exists::<BossConfig>())
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// region: systems
+
+#[allow(clippy::too_many_arguments)]
+fn s_boss_spawner(
+    mut commands: Commands,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut schedule: ResMut<BossSchedule>,
+    boss_config: Res<BossConfig>,
+    enemy_config: Res<EnemyConfig>,
+    arena: Res<Arena>,
+    players: Res<Players>,
+    player_query: Query<&CTransform, With<TPlayer>>,
+) {
+    let score = players.0.iter().map(|p| p.score).max().unwrap_or(0);
+    let waves = schedule.spawned / boss_config.wave_size.max(1);
+    for (kind, boss) in boss_config.bosses.iter().enumerate() {
+        let reached = match boss.milestone {
+            Milestone::Score(points) => score >= points,
+            Milestone::Wave(wave) => waves >= wave,
+        };
+        if !reached || schedule.fought.contains(&kind) || boss.phases.is_empty() {
+            continue;
+        }
+        schedule.fought.push(kind);
+        info!("Boss: {}", boss.name);
+
+        let threats: Vec<Vec2> = player_query.iter().map(|tf| tf.pos).collect();
+        let pos = safest_point(&arena, boss.shape_radius, &threats);
+        let vel = Vec2::from_angle(rng.range_f32(0., TAU)) * boss.phases[0].speed;
+        spawn_boss(&mut commands, kind, boss, pos, vel, &enemy_config);
+    }
+}
+
+fn s_boss_patterns(
+    mut commands: Commands,
+    mut bosses: Query<(&mut CBoss, &CHealth, &mut CTransform), Without<CTelegraph>>,
+    player_query: Query<&CTransform, (With<TPlayer>, Without<CBoss>)>,
+    boss_config: Res<BossConfig>,
+    enemy_config: Res<EnemyConfig>,
+    time: Res<Time<Fixed>>,
+    time_scale: Res<TimeScale>,
+) {
+    let scale = enemy_config.difficulty.fire_rate_scale();
+    for (mut boss, health, mut tf) in bosses.iter_mut() {
+        let Some(kind) = boss_config.bosses.get(boss.kind) else {
+            continue;
+        };
+        let ratio = health.current as f32 / health.max.max(1) as f32;
+        let phase = kind
+            .phases
+            .iter()
+            .rposition(|p| ratio <= p.health)
+            .unwrap_or(0);
+        let Some(phase_config) = kind.phases.get(phase) else {
+            continue;
+        };
+        if phase != boss.phase {
+            boss.phase = phase;
+            let dir = tf.vel.try_normalize().unwrap_or(Vec2::X);
+            tf.vel = dir * phase_config.speed;
+        }
+        let rate = phase_config.fire_rate * scale;
+        if rate <= 0. {
+            continue;
+        }
+
+        boss.cooldown -= time_scale.delta(&time);
+        while boss.cooldown <= 0. {
+            boss.cooldown += 1. / rate;
+            let angles: Vec<f32> = match phase_config.pattern {
+                Pattern::Ring { count } => {
+                    let step = TAU / count.max(1) as f32;
+                    let angles = (0..count).map(|i| boss.spin + step * i as f32).collect();
+                    // every other ring fills the gaps of the one before
+                    boss.spin += step / 2.;
+                    angles
+                }
+                Pattern::Spiral { arms, turn } => {
+                    let step = TAU / arms.max(1) as f32;
+                    let angles = (0..arms).map(|i| boss.spin + step * i as f32).collect();
+                    boss.spin += turn.to_radians();
+                    angles
+                }
+                Pattern::Aimed { count, spread } => {
+                    let Some(target) = player_query
+                        .iter()
+                        .map(|p| p.pos)
+                        .min_by(|a, b| a.distance(tf.pos).total_cmp(&b.distance(tf.pos)))
+                    else {
+                        continue;
+                    };
+                    let aim = target - tf.pos;
+                    fan(aim.y.atan2(aim.x), count, spread.to_radians())
+                }
+            };
+            for angle in angles {
+                spawn_projectile(
+                    &mut commands,
+                    tf.pos,
+                    Vec2::from_angle(angle) * phase_config.projectile_speed,
+                    &boss_config.projectile,
+                );
+            }
+        }
+        boss.spin %= TAU;
+    }
+}
+
+/// Takes a point of health for every bullet that hits a boss, bursting it when none are left.
+/// A piercing bullet hits each boss once on its way through.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn s_boss_hits(
+    mut commands: Commands,
+    mut players: ResMut<Players>,
+    mut combo: ResMut<Combo>,
+    mut pools: ResMut<Pools>,
+    mut bullet_query: Query<
+        (
+            Entity,
+            &CTransform,
+            &CCollision,
+            &CShape,
+            &COwner,
+            Option<&mut CPierce>,
+            Option<&CStruck>,
+        ),
+        (With<TBullet>, Without<TInactive>),
+    >,
+    mut boss_query: Query<
+        (
+            Entity,
+            &CTransform,
+            &CCollision,
+            &CShape,
+            &CScore,
+            &CBoss,
+            &mut CHealth,
+        ),
+        (Without<CTelegraph>, Without<TBullet>),
+    >,
+    boss_config: Res<BossConfig>,
+    enemy_config: Res<EnemyConfig>,
+    score_config: Res<ScoreConfig>,
+    mut events: EventWriter<GameEvent>,
+    mut collisions: ResMut<CollisionCounts>,
+) {
+    // bullets used up earlier this tick, their release hasn't been applied yet
+    let mut spent = Vec::new();
+    for (boss_e, boss_tf, boss_c, boss_sh, boss_sc, boss, mut health) in boss_query.iter_mut() {
+        for (b_e, b_tf, b_c, b_sh, owner, pierce, struck) in bullet_query.iter_mut() {
+            if spent.contains(&b_e)
+                || struck.is_some_and(|struck| struck.0 == boss_e)
+                || !collisions.record(is_collision(boss_tf, boss_c, boss_sh, b_tf, b_c, b_sh))
+            {
+                continue;
+            }
+            match pierce {
+                Some(mut pierce) if pierce.0 > 0 => {
+                    pierce.0 -= 1;
+                    commands.entity(b_e).insert(CStruck(boss_e));
+                }
+                _ => {
+                    pools.release(&mut commands, b_e);
+                    spent.push(b_e);
+                }
+            }
+            events.send(GameEvent::Hit {
+                slot: owner.0,
+                bullet: b_e,
+            });
+            health.current = health.current.saturating_sub(1);
+            if health.current > 0 {
+                continue;
+            }
+
+            commands.entity(boss_e).despawn();
+            let points = (boss_sc.0 as f32 * combo.multiplier).round() as u32;
+            if let Some(state) = players.0.get_mut(owner.0) {
+                state.score += points;
+            }
+            combo.multiplier =
+                (combo.multiplier + score_config.combo_step).min(score_config.max_multiplier);
+            combo.since_kill = 0.;
+            events.send(GameEvent::Kill {
+                slot: owner.0,
+                vertices: boss_sh.vertices,
+            });
+            spawn_popup(&mut commands, boss_tf.pos, points, &score_config);
+            let rings = boss_config
+                .bosses
+                .get(boss.kind)
+                .map_or(1, |b| b.explosion_rings.max(1));
+            // each ring turned to fall between the fragments of the others
+            for ring in 0..rings {
+                let mut ring_tf = boss_tf.clone();
+                ring_tf.angle += TAU * ring as f32 / (boss_sh.vertices * rings) as f32;
+                spawn_small_enemies(
+                    &mut commands,
+                    &mut pools.fragments,
+                    &ring_tf,
+                    boss_sh,
+                    &enemy_config,
+                    *owner,
+                );
+            }
+            break;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_boss_bars(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font_config: Res<FontConfig>,
+    boss_config: Res<BossConfig>,
+    arena: Res<Arena>,
+    bosses: Query<(Entity, &CBoss, &CHealth, &CShape)>,
+    mut bars: Query<(Entity, &TBossBar, &mut Transform, &Children)>,
+    mut fills: Query<&mut Sprite, With<TBossBarFill>>,
+) {
+    let width = arena.width * 0.6;
+    let height = 12.;
+    let mut shown = Vec::new();
+    for (i, (bar_e, bar, mut transform, children)) in bars.iter_mut().enumerate() {
+        let Ok((_, _, health, _)) = bosses.get(bar.0) else {
+            commands.entity(bar_e).despawn_recursive();
+            continue;
+        };
+        shown.push(bar.0);
+        transform.translation = Vec3::new(
+            (arena.width - width) / 2.,
+            arena.height - 24. - i as f32 * (height + font_config.size),
+            3.,
+        );
+        for child in children.iter() {
+            if let Ok(mut sprite) = fills.get_mut(*child) {
+                let ratio = health.current as f32 / health.max.max(1) as f32;
+                sprite.custom_size = Some(Vec2::new(width * ratio, height));
+            }
+        }
+    }
+
+    for (boss_e, boss, _, shape) in bosses.iter() {
+        if shown.contains(&boss_e) {
+            continue;
+        }
+        let name = boss_config
+            .bosses
+            .get(boss.kind)
+            .map_or("", |b| b.name.as_str());
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(0.2, 0.2, 0.2, 0.8),
+                        custom_size: Some(Vec2::new(width, height)),
+                        anchor: Anchor::CenterLeft,
+                        ..Default::default()
+                    },
+                    transform: Transform::from_xyz(0., arena.height + height, 3.),
+                    ..Default::default()
+                },
+                TBossBar(boss_e),
+            ))
+            .with_children(|bar| {
+                bar.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: shape.color.with_a(1.),
+                            custom_size: Some(Vec2::new(width, height)),
+                            anchor: Anchor::CenterLeft,
+                            ..Default::default()
+                        },
+                        transform: Transform::from_xyz(0., 0., 0.1),
+                        ..Default::default()
+                    },
+                    TBossBarFill,
+                ));
+                bar.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        name,
+                        TextStyle {
+                            font: asset_server.load(font_config.file.clone()),
+                            font_size: font_config.size / 2.,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    text_anchor: Anchor::BottomLeft,
+                    transform: Transform::from_xyz(0., height / 2. + 2., 0.1),
+                    ..Default::default()
+                });
+            });
+    }
+}
+
+// endregion
+
+// region: functions
+
+fn spawn_boss(
+    commands: &mut Commands,
+    kind: usize,
+    boss: &BossType,
+    pos: Vec2,
+    vel: Vec2,
+    enemy_config: &EnemyConfig,
+) {
+    let mut e = commands.spawn((
+        CTransform::new(pos, vel, 0.),
+        CShape {
+            radius: boss.shape_radius,
+            color: rgb(boss.color),
+            outline: rgb(boss.outline_color),
+            thickness: boss.outline_thickness,
+            vertices: boss.vertices,
+        },
+        CCollision {
+            rad: boss.collision_radius,
+            mode: CollisionMode::Circle,
+        },
+        CScore(boss.score),
+        CBoundary(BoundaryMode::Bounce),
+        CHealth {
+            current: boss.health.max(1),
+            max: boss.health.max(1),
+        },
+        CBoss {
+            kind,
+            phase: 0,
+            cooldown: 0.,
+            spin: 0.,
+        },
+        THostile,
+    ));
+    // bosses show their outline for a while first, like any enemy
+    if enemy_config.telegraph_time > 0. {
+        e.insert(CTelegraph {
+            remaining: enemy_config.telegraph_time,
+            total: enemy_config.telegraph_time,
+        });
+    }
+}
+
+/// `count` angles centered on `aim` and spread evenly over `spread` radians.
+fn fan(aim: f32, count: u32, spread: f32) -> Vec<f32> {
+    if count <= 1 {
+        return vec![aim];
+    }
+    (0..count)
+        .map(|i| aim - spread / 2. + spread * i as f32 / (count - 1) as f32)
+        .collect()
+}
+
+// endregion
@@ -0,0 +1,168 @@
+// region: includes
+
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use crate::config::CollisionMode;
+use crate::{CCollision, CShape, CTransform};
+
+// endregion
+
+// region: functions
+
+/// Circle broadphase followed by a separating axis test for entities in polygon mode.
+///
+/// Polygon entities use the circle circumscribing their shape for the broadphase, so a
+/// collision radius smaller than the shape can never hide a real overlap.
+pub fn is_collision(
+    tf1: &CTransform,
+    c1: &CCollision,
+    sh1: &CShape,
+    tf2: &CTransform,
+    c2: &CCollision,
+    sh2: &CShape,
+) -> bool {
+    if !circles_overlap(
+        tf1.pos,
+        broad_radius(c1, sh1),
+        tf2.pos,
+        broad_radius(c2, sh2),
+    ) {
+        return false;
+    }
+    match (c1.mode, c2.mode) {
+        (CollisionMode::Circle, CollisionMode::Circle) => true,
+        (CollisionMode::Polygon, CollisionMode::Polygon) => {
+            polygons_overlap(&polygon(tf1, sh1), &polygon(tf2, sh2))
+        }
+        (CollisionMode::Polygon, CollisionMode::Circle) => {
+            polygon_circle_overlap(&polygon(tf1, sh1), tf2.pos, c2.rad)
+        }
+        (CollisionMode::Circle, CollisionMode::Polygon) => {
+            polygon_circle_overlap(&polygon(tf2, sh2), tf1.pos, c1.rad)
+        }
+    }
+}
+
+pub fn circles_overlap(pos1: Vec2, rad1: f32, pos2: Vec2, rad2: f32) -> bool {
+    pos1.distance_squared(pos2) < (rad1 + rad2) * (rad1 + rad2)
+}
+
+fn broad_radius(c: &CCollision, sh: &CShape) -> f32 {
+    match c.mode {
+        CollisionMode::Circle => c.rad,
+        CollisionMode::Polygon => sh.radius,
+    }
+}
+
+/// World space corners of the shape, in the same order and rotation `Gizmos::arc_2d` draws them.
+pub fn polygon(tf: &CTransform, sh: &CShape) -> Vec<Vec2> {
+    let start = tf.angle - PI;
+    (0..sh.vertices)
+        .map(|i| {
+            let angle = start + i as f32 * TAU / sh.vertices as f32;
+            tf.pos + Vec2::from(angle.sin_cos()) * sh.radius
+        })
+        .collect()
+}
+
+/// Separating axis test between two convex polygons.
+pub fn polygons_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    edge_normals(a)
+        .chain(edge_normals(b))
+        .filter(|axis| *axis != Vec2::ZERO)
+        .all(|axis| intervals_overlap(project(a, axis), project(b, axis)))
+}
+
+/// Separating axis test between a convex polygon and a circle. Besides the edge normals the
+/// circle can only be separated along the axis towards the polygon's closest corner.
+pub fn polygon_circle_overlap(poly: &[Vec2], center: Vec2, rad: f32) -> bool {
+    let Some(closest) = poly.iter().min_by(|p, q| {
+        p.distance_squared(center)
+            .total_cmp(&q.distance_squared(center))
+    }) else {
+        return false;
+    };
+    let circle = |axis: Vec2| {
+        let c = center.dot(axis);
+        (c - rad, c + rad)
+    };
+    edge_normals(poly)
+        .chain(std::iter::once((center - *closest).normalize_or_zero()))
+        .filter(|axis| *axis != Vec2::ZERO)
+        .all(|axis| intervals_overlap(project(poly, axis), circle(axis)))
+}
+
+/// Elastic collision between two circles: moves them apart if they overlap, the lighter one
+/// further, and exchanges their velocity along the line between them if they are closing in.
+/// Returns whether they touched.
+pub fn bounce_apart(
+    tf1: &mut CTransform,
+    rad1: f32,
+    mass1: f32,
+    tf2: &mut CTransform,
+    rad2: f32,
+    mass2: f32,
+) -> bool {
+    let Some((normal, overlap)) = contact(tf1, rad1, tf2, rad2) else {
+        return false;
+    };
+    let total = mass1 + mass2;
+    tf1.pos -= normal * overlap * mass2 / total;
+    tf2.pos += normal * overlap * mass1 / total;
+    // a pair already moving apart keeps its velocity, so it doesn't stick together
+    let closing = (tf1.vel - tf2.vel).dot(normal);
+    if closing > 0. {
+        tf1.vel -= normal * closing * 2. * mass2 / total;
+        tf2.vel += normal * closing * 2. * mass1 / total;
+    }
+    true
+}
+
+/// Moves two overlapping circles apart by half the overlap each, keeping their velocities.
+/// Returns whether they touched.
+pub fn push_apart(tf1: &mut CTransform, rad1: f32, tf2: &mut CTransform, rad2: f32) -> bool {
+    let Some((normal, overlap)) = contact(tf1, rad1, tf2, rad2) else {
+        return false;
+    };
+    tf1.pos -= normal * overlap / 2.;
+    tf2.pos += normal * overlap / 2.;
+    true
+}
+
+/// The direction from the first circle to the second and how deep they overlap, if they do.
+/// Circles on the same spot, like fragments just spawned, part along their relative velocity.
+fn contact(tf1: &CTransform, rad1: f32, tf2: &CTransform, rad2: f32) -> Option<(Vec2, f32)> {
+    let delta = tf2.pos - tf1.pos;
+    let overlap = rad1 + rad2 - delta.length();
+    if overlap <= 0. {
+        return None;
+    }
+    let normal = delta
+        .try_normalize()
+        .or_else(|| (tf2.vel - tf1.vel).try_normalize())
+        .unwrap_or(Vec2::X);
+    Some((normal, overlap))
+}
+
+fn edge_normals(poly: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    (0..poly.len()).map(move |i| {
+        let edge = poly[(i + 1) % poly.len()] - poly[i];
+        edge.perp().normalize_or_zero()
+    })
+}
+
+fn project(poly: &[Vec2], axis: Vec2) -> (f32, f32) {
+    poly.iter()
+        .map(|p| p.dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), d| {
+            (min.min(d), max.max(d))
+        })
+}
+
+fn intervals_overlap(a: (f32, f32), b: (f32, f32)) -> bool {
+    a.0 < b.1 && b.0 < a.1
+}
+
+// endregion
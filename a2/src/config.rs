@@ -3,18 +3,57 @@
 use bevy::prelude::*;
 use bevy::reflect::TypePath;
 use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use comp4300_common::cli::Cli;
+use comp4300_common::rng::{resolve_seed, RngSeed};
+use comp4300_common::telemetry::TelemetryFormat;
 
 use crate::AppState;
 
 // endregion
 
+pub const MAX_PLAYERS: usize = 4;
+
 // region: Config Structs
 
+/// How an entity kind is tested for collisions. `Polygon` still uses the circle test as a
+/// broadphase before the exact test against the rotated shape.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CollisionMode {
+    Circle,
+    Polygon,
+}
+
+/// What an entity does when it reaches the edge of the window.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoundaryMode {
+    /// reflect off the edge
+    Bounce,
+    /// leave one side and come back in on the opposite one
+    Wrap,
+    /// despawn once fully outside the window
+    Despawn,
+    /// stop at the edge
+    Clamp,
+}
+
+/// How shapes are drawn.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Renderer {
+    /// outlines only, in the fill color, redrawn with gizmos every frame
+    #[default]
+    Gizmos,
+    /// filled polygon meshes with a separate outline mesh in the outline color
+    Mesh,
+}
+
 #[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
 pub struct WindowConfig {
     pub size: (f32, f32),
     pub frame_limit: f32,
     pub fullscreen: bool,
+    #[serde(default)]
+    pub renderer: Renderer,
 }
 
 #[derive(Resource, Debug)]
@@ -30,13 +69,70 @@ pub struct FontConfig {
 #[derive(Resource, Debug)]
 struct FontHandle(Handle<FontConfig>);
 
+/// How one player is controlled.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Controls {
+    /// move with keys, aim with the cursor and fire with the left mouse button
+    KeyboardMouse {
+        up: KeyCode,
+        down: KeyCode,
+        left: KeyCode,
+        right: KeyCode,
+        prev_weapon: KeyCode,
+        next_weapon: KeyCode,
+        /// held for slow motion
+        #[serde(default = "default_focus_key")]
+        focus: KeyCode,
+    },
+    /// move with keys and fire in the direction last moved
+    Keyboard {
+        up: KeyCode,
+        down: KeyCode,
+        left: KeyCode,
+        right: KeyCode,
+        shoot: KeyCode,
+        prev_weapon: KeyCode,
+        next_weapon: KeyCode,
+        #[serde(default = "default_focus_key")]
+        focus: KeyCode,
+    },
+    /// the gamepad with this id: left stick moves, right stick aims, right trigger fires, the
+    /// bumpers cycle weapons and the left trigger focuses
+    Gamepad(usize),
+}
+
+fn default_focus_key() -> KeyCode {
+    KeyCode::ShiftLeft
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct PlayerSlot {
+    pub color: (f32, f32, f32),
+    pub controls: Controls,
+}
+
 #[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
 pub struct PlayerConfig {
     pub shape_radius: f32,
     pub collision_radius: f32,
+    pub collision_mode: CollisionMode,
+    pub boundary: BoundaryMode,
     pub speed: f32,
-    pub color: (f32, f32, f32),
+    pub outline_color: (f32, f32, f32),
+    pub outline_thickness: f32,
     pub vertices: u32,
+    /// deaths each player can take before the game is over for them
+    pub lives: u32,
+    /// players in the game, taking the first `count` slots
+    pub count: usize,
+    pub slots: Vec<PlayerSlot>,
+}
+
+impl PlayerConfig {
+    /// Players actually in the game, `count` kept between 1 and 4 and to the slots configured.
+    pub fn players(&self) -> usize {
+        self.count.clamp(1, MAX_PLAYERS).min(self.slots.len())
+    }
 }
 
 #[derive(Resource, Debug)]
@@ -46,30 +142,345 @@ struct PlayerHandle(Handle<PlayerConfig>);
 pub struct EnemyConfig {
     pub shape_radius: f32,
     pub collision_radius: f32,
+    pub collision_mode: CollisionMode,
+    pub boundary: BoundaryMode,
     pub min_speed: f32,
     pub max_speed: f32,
+    pub outline_color: (f32, f32, f32),
+    pub outline_thickness: f32,
     pub min_vertices: u32,
     pub max_vertices: u32,
     pub small_lifespan: f32,
+    pub small_speed: f32,
+    pub small_boundary: BoundaryMode,
+    /// fragments kept around for reuse instead of being despawned
+    pub small_pool_size: usize,
     pub spawn_interval: f32,
+    /// closest an enemy may appear to the player
+    pub min_spawn_distance: f32,
+    /// seconds an enemy's outline shows before it moves and collides
+    pub telegraph_time: f32,
+    #[serde(default)]
+    pub palette: Vec<(f32, f32, f32)>,
+    /// shots per second each enemy fires at the nearest player, 0 for none
+    pub fire_rate: f32,
+    pub projectile_speed: f32,
+    /// 1 fires straight at the player, 0 up to 90 degrees off
+    pub accuracy: f32,
+    pub projectile: ProjectileType,
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub crowding: Crowding,
+}
+
+/// How enemies treat each other when they meet.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Crowding {
+    /// they pass through each other
+    #[default]
+    Overlap,
+    /// enemies bounce off each other by their collision radius, heavier ones (more vertices)
+    /// pushing lighter ones further, and fragments push each other apart
+    Bounce,
+}
+
+/// Scales how often enemies and bosses fire.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn fire_rate_scale(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.5,
+        }
+    }
 }
 
 #[derive(Resource, Debug)]
 struct EnemyHandle(Handle<EnemyConfig>);
 
-#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
-pub struct BulletConfig {
+/// One weapon the player can cycle to. Everything below `lifespan` is optional.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct BulletType {
+    pub name: String,
     pub shape_radius: f32,
     pub collision_radius: f32,
+    pub collision_mode: CollisionMode,
     pub speed: f32,
+    /// fill color
     pub color: (f32, f32, f32),
+    pub outline_color: (f32, f32, f32),
+    pub outline_thickness: f32,
     pub vertices: u32,
     pub lifespan: f32,
+    /// bullets per shot, fanned out evenly over `spread` degrees
+    #[serde(default = "default_count")]
+    pub count: u32,
+    #[serde(default)]
+    pub spread: f32,
+    /// enemies a bullet passes through before it is used up
+    #[serde(default)]
+    pub pierce: u32,
+    /// radians per second the bullet turns towards the nearest enemy
+    #[serde(default)]
+    pub homing: f32,
+    /// overrides `BulletConfig::boundary` for this type
+    #[serde(default)]
+    pub boundary: Option<BoundaryMode>,
+    /// wall bounces before the bullet despawns in `Bounce` mode, None for no limit
+    #[serde(default)]
+    pub bounces: Option<u32>,
+    /// shots per second, 0 for no limit
+    #[serde(default)]
+    pub fire_rate: f32,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct BulletConfig {
+    pub boundary: BoundaryMode,
+    /// bullets kept around for reuse instead of being despawned
+    pub pool_size: usize,
+    pub types: Vec<BulletType>,
 }
 
 #[derive(Resource, Debug)]
 struct BulletHandle(Handle<BulletConfig>);
 
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct ScoreConfig {
+    pub combo_step: f32,
+    pub max_multiplier: f32,
+    pub combo_window: f32,
+    pub combo_decay: f32,
+    pub popup_lifespan: f32,
+    pub popup_speed: f32,
+    pub popup_size: f32,
+}
+
+#[derive(Resource, Debug)]
+struct ScoreHandle(Handle<ScoreConfig>);
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct RngConfig {
+    pub seed: Option<u64>,
+}
+
+#[derive(Resource, Debug)]
+struct RngHandle(Handle<RngConfig>);
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplayMode {
+    Off,
+    Record,
+    Playback,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct ReplayConfig {
+    pub mode: ReplayMode,
+    pub file: String,
+    pub speed: f32,
+    pub seek_frames: usize,
+}
+
+#[derive(Resource, Debug)]
+struct ReplayHandle(Handle<ReplayConfig>);
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NetMode {
+    Off,
+    /// runs the game and takes slot 0, remote players join the other slots
+    Server,
+    /// only sends input and shows what the server sends back
+    Client,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Clone, Debug)]
+pub struct NetConfig {
+    pub mode: NetMode,
+    /// where the server listens and clients connect to
+    pub address: String,
+    /// seconds clients show the world behind the newest snapshot, to smooth over late packets
+    pub interpolation_delay: f32,
+    /// simulated one-way delay in seconds on everything this side sends
+    pub lag: f32,
+    /// random extra delay in seconds, up to this much, on top of `lag`
+    pub jitter: f32,
+    /// fraction of packets this side sends that are dropped
+    pub loss: f32,
+}
+
+#[derive(Resource, Debug)]
+struct NetHandle(Handle<NetConfig>);
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct SaveConfig {
+    /// directory the save slot files are written to
+    pub dir: String,
+    pub slots: usize,
+}
+
+#[derive(Resource, Debug)]
+struct SaveHandle(Handle<SaveConfig>);
+
+/// What has to happen for an achievement to unlock, counted over every session.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub enum Goal {
+    /// kills of enemies with this many vertices, or of any enemy
+    Kills {
+        vertices: Option<u32>,
+        count: u64,
+    },
+    Shots(u64),
+    Hits(u64),
+    /// percent of shots that hit, once at least `shots` have been fired
+    Accuracy {
+        percent: f32,
+        shots: u64,
+    },
+    /// seconds alive without dying
+    Survive(f32),
+    Score(u32),
+    Deaths(u64),
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct Achievement {
+    pub name: String,
+    pub description: String,
+    pub goal: Goal,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct StatsConfig {
+    /// file the stats and unlocked achievements are kept in between sessions
+    pub file: String,
+    /// seconds an unlocked achievement's toast stays on screen
+    pub toast_time: f32,
+    pub achievements: Vec<Achievement>,
+}
+
+#[derive(Resource, Debug)]
+struct StatsHandle(Handle<StatsConfig>);
+
+/// When a boss appears, once per game.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Milestone {
+    /// any player's score reaches this
+    Score(u32),
+    /// this many waves of `BossConfig::wave_size` enemies have spawned
+    Wave(u32),
+}
+
+/// How a boss fires one volley.
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Pattern {
+    /// `count` projectiles spread evenly around the boss
+    Ring { count: u32 },
+    /// `arms` streams that turn `turn` degrees between volleys
+    Spiral { arms: u32, turn: f32 },
+    /// `count` projectiles at the nearest player, fanned out over `spread` degrees
+    Aimed { count: u32, spread: f32 },
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct BossPhase {
+    /// fraction of the boss' health at or below which this phase takes over
+    pub health: f32,
+    pub pattern: Pattern,
+    /// volleys per second
+    pub fire_rate: f32,
+    pub projectile_speed: f32,
+    /// the boss' own speed during the phase
+    pub speed: f32,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct BossType {
+    pub name: String,
+    pub milestone: Milestone,
+    pub shape_radius: f32,
+    pub collision_radius: f32,
+    pub color: (f32, f32, f32),
+    pub outline_color: (f32, f32, f32),
+    pub outline_thickness: f32,
+    pub vertices: u32,
+    /// bullets it takes to destroy
+    pub health: u32,
+    pub score: u32,
+    /// rings of fragments it bursts into, each as many as its vertices
+    pub explosion_rings: u32,
+    /// in order, from full health down
+    pub phases: Vec<BossPhase>,
+}
+
+/// What bosses and enemies fire. Projectiles hurt players and pass through everything else.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ProjectileType {
+    pub shape_radius: f32,
+    pub collision_radius: f32,
+    pub color: (f32, f32, f32),
+    pub outline_color: (f32, f32, f32),
+    pub outline_thickness: f32,
+    pub vertices: u32,
+    pub lifespan: f32,
+}
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct BossConfig {
+    /// enemies per wave, for `Milestone::Wave`
+    pub wave_size: u32,
+    /// whether normal enemies stop spawning while a boss is alive
+    pub pause_spawns: bool,
+    pub projectile: ProjectileType,
+    pub bosses: Vec<BossType>,
+}
+
+#[derive(Resource, Debug)]
+struct BossHandle(Handle<BossConfig>);
+
+/// Slow motion a player triggers by holding their focus control, paid for from a meter that
+/// refills while nobody is focusing.
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct FocusConfig {
+    /// time scale of the whole game while focusing
+    pub scale: f32,
+    /// seconds the time scale takes to ease between 1 and `scale`
+    pub transition: f32,
+    /// seconds of focus in a full meter
+    pub duration: f32,
+    /// seconds an empty meter takes to fill up again
+    pub recharge: f32,
+    /// fraction of the meter needed to start focusing
+    pub min_start: f32,
+}
+
+#[derive(Resource, Debug)]
+struct FocusHandle(Handle<FocusConfig>);
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct TelemetryConfig {
+    /// write a row for every frame while in game
+    pub enabled: bool,
+    pub format: TelemetryFormat,
+    /// file the rows are written to, replaced every session
+    pub file: String,
+}
+
+#[derive(Resource, Debug)]
+struct TelemetryHandle(Handle<TelemetryConfig>);
+
 // endregion
 
 pub struct ConfigPlugin;
@@ -82,36 +493,76 @@ impl Plugin for ConfigPlugin {
             RonAssetPlugin::<PlayerConfig>::new(&["player.ron"]),
             RonAssetPlugin::<EnemyConfig>::new(&["enemy.ron"]),
             RonAssetPlugin::<BulletConfig>::new(&["bullet.ron"]),
+            RonAssetPlugin::<ScoreConfig>::new(&["score.ron"]),
+            RonAssetPlugin::<RngConfig>::new(&["rng.ron"]),
+            RonAssetPlugin::<ReplayConfig>::new(&["replay.ron"]),
+            RonAssetPlugin::<NetConfig>::new(&["net.ron"]),
+            RonAssetPlugin::<SaveConfig>::new(&["save.ron"]),
+            RonAssetPlugin::<StatsConfig>::new(&["stats.ron"]),
+            RonAssetPlugin::<BossConfig>::new(&["boss.ron"]),
+            RonAssetPlugin::<TelemetryConfig>::new(&["telemetry.ron"]),
+            RonAssetPlugin::<FocusConfig>::new(&["focus.ron"]),
         ))
+        .init_resource::<Cli>()
         .add_systems(Startup, setup)
-        .add_systems(OnEnter(AppState::Loading), load_resources);
+        .add_systems(
+            OnEnter(AppState::Loading),
+            (load_resources, load_more_resources, seed_rng),
+        );
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, cli: Res<Cli>) {
     info!("Setup function running");
-    let window_config = WindowHandle(asset_server.load("config/config.window.ron"));
+    let window_config = WindowHandle(asset_server.load(cli.config_path("config.window.ron")));
     commands.insert_resource(window_config);
 
-    let font_config = FontHandle(asset_server.load("config/config.font.ron"));
+    let font_config = FontHandle(asset_server.load(cli.config_path("config.font.ron")));
     commands.insert_resource(font_config);
 
-    let player_config = PlayerHandle(asset_server.load("config/config.player.ron"));
+    let player_config = PlayerHandle(asset_server.load(cli.config_path("config.player.ron")));
     commands.insert_resource(player_config);
 
-    let enemy_config = EnemyHandle(asset_server.load("config/config.enemy.ron"));
+    let enemy_config = EnemyHandle(asset_server.load(cli.config_path("config.enemy.ron")));
     commands.insert_resource(enemy_config);
 
-    let bullet_config = BulletHandle(asset_server.load("config/config.bullet.ron"));
+    let bullet_config = BulletHandle(asset_server.load(cli.config_path("config.bullet.ron")));
     commands.insert_resource(bullet_config);
+
+    let score_config = ScoreHandle(asset_server.load(cli.config_path("config.score.ron")));
+    commands.insert_resource(score_config);
+
+    let rng_config = RngHandle(asset_server.load(cli.config_path("config.rng.ron")));
+    commands.insert_resource(rng_config);
+
+    let replay_config = ReplayHandle(asset_server.load(cli.config_path("config.replay.ron")));
+    commands.insert_resource(replay_config);
+
+    let net_config = NetHandle(asset_server.load(cli.config_path("config.net.ron")));
+    commands.insert_resource(net_config);
+
+    let save_config = SaveHandle(asset_server.load(cli.config_path("config.save.ron")));
+    commands.insert_resource(save_config);
+
+    let stats_config = StatsHandle(asset_server.load(cli.config_path("config.stats.ron")));
+    commands.insert_resource(stats_config);
+
+    let boss_config = BossHandle(asset_server.load(cli.config_path("config.boss.ron")));
+    commands.insert_resource(boss_config);
+
+    let telemetry_config =
+        TelemetryHandle(asset_server.load(cli.config_path("config.telemetry.ron")));
+    commands.insert_resource(telemetry_config);
+
+    let focus_config = FocusHandle(asset_server.load(cli.config_path("config.focus.ron")));
+    commands.insert_resource(focus_config);
     info!("Setup function ended");
 }
 
+#[allow(clippy::too_many_arguments)]
 fn load_resources(
     mut commands: Commands,
     mut state: ResMut<NextState<AppState>>,
-    window_handle: Res<WindowHandle>,
-    mut window_configs: ResMut<Assets<WindowConfig>>,
     font_handle: Res<FontHandle>,
     mut font_configs: ResMut<Assets<FontConfig>>,
     player_handle: Res<PlayerHandle>,
@@ -120,12 +571,12 @@ fn load_resources(
     mut enemy_configs: ResMut<Assets<EnemyConfig>>,
     bullet_handle: Res<BulletHandle>,
     mut bullet_configs: ResMut<Assets<BulletConfig>>,
+    score_handle: Res<ScoreHandle>,
+    mut score_configs: ResMut<Assets<ScoreConfig>>,
+    replay_handle: Res<ReplayHandle>,
+    mut replay_configs: ResMut<Assets<ReplayConfig>>,
 ) {
     info!("Load Resources running");
-    if let Some(r) = window_configs.remove(window_handle.0.id()) {
-        commands.insert_resource(r);
-    }
-
     if let Some(r) = font_configs.remove(font_handle.0.id()) {
         commands.insert_resource(r);
     }
@@ -142,6 +593,84 @@ fn load_resources(
         commands.insert_resource(r);
     }
 
+    if let Some(r) = score_configs.remove(score_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+
+    if let Some(r) = replay_configs.remove(replay_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+
     info!("Load Resources ended");
     state.set(AppState::InGame);
 }
+
+/// The configs that no longer fit in `load_resources`' system parameters.
+#[allow(clippy::too_many_arguments)]
+fn load_more_resources(
+    mut commands: Commands,
+    window_handle: Res<WindowHandle>,
+    mut window_configs: ResMut<Assets<WindowConfig>>,
+    net_handle: Res<NetHandle>,
+    mut net_configs: ResMut<Assets<NetConfig>>,
+    save_handle: Res<SaveHandle>,
+    mut save_configs: ResMut<Assets<SaveConfig>>,
+    stats_handle: Res<StatsHandle>,
+    mut stats_configs: ResMut<Assets<StatsConfig>>,
+    boss_handle: Res<BossHandle>,
+    mut boss_configs: ResMut<Assets<BossConfig>>,
+    telemetry_handle: Res<TelemetryHandle>,
+    mut telemetry_configs: ResMut<Assets<TelemetryConfig>>,
+    focus_handle: Res<FocusHandle>,
+    mut focus_configs: ResMut<Assets<FocusConfig>>,
+    cli: Res<Cli>,
+) {
+    if let Some(mut r) = window_configs.remove(window_handle.0.id()) {
+        if let Some(size) = cli.window_size {
+            r.size = size;
+        }
+        r.fullscreen |= cli.fullscreen;
+        commands.insert_resource(r);
+    }
+
+    if let Some(r) = net_configs.remove(net_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+
+    if let Some(r) = save_configs.remove(save_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+
+    if let Some(r) = stats_configs.remove(stats_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+
+    if let Some(r) = boss_configs.remove(boss_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+
+    // only kept when enabled, the telemetry systems run while the resource exists
+    if let Some(r) = telemetry_configs.remove(telemetry_handle.0.id()) {
+        if r.enabled {
+            commands.insert_resource(r);
+        }
+    }
+
+    if let Some(r) = focus_configs.remove(focus_handle.0.id()) {
+        commands.insert_resource(r);
+    }
+}
+
+fn seed_rng(
+    mut commands: Commands,
+    rng_handle: Res<RngHandle>,
+    mut rng_configs: ResMut<Assets<RngConfig>>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    cli: Res<Cli>,
+) {
+    let config_seed = rng_configs.remove(rng_handle.0.id()).and_then(|r| r.seed);
+    let seed = resolve_seed(cli.seed.or(config_seed));
+    rng.reseed(seed.to_le_bytes());
+    commands.insert_resource(RngSeed(seed));
+    info!("RNG seed: {}", seed);
+}
@@ -0,0 +1,183 @@
+// region: includes
+
+use std::f32::consts::PI;
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::config::FontConfig;
+use crate::pool::TInactive;
+use crate::settings;
+use crate::{AppState, Arena, CCollision, CTransform, TBullet, TEnemy, TPlayer, TimeScale};
+
+// endregion
+
+// time scales F2 steps through, to test the game slowed down or sped up
+const TIME_SCALES: [f32; 5] = [1., 0.5, 0.25, 0.1, 2.];
+
+// region: Debug Structs
+
+/// Which debug layers are drawn, toggled with T (shapes), C (collision radii and velocities)
+/// and G (64px grid and the stats panel). F2 steps through time scales.
+#[derive(Resource)]
+pub struct DebugOverlay {
+    pub shapes: bool,
+    pub collision: bool,
+    pub grid: bool,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self {
+            shapes: true,
+            collision: false,
+            grid: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct TDebugText;
+
+// endregion
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FrameTimeDiagnosticsPlugin)
+            .init_resource::<DebugOverlay>()
+            .add_systems(OnEnter(AppState::InGame), s_setup_debug_text)
+            .add_systems(
+                Update,
+                (
+                    s_debug_toggles.run_if(settings::closed),
+                    s_debug_draw,
+                    s_debug_text,
+                )
+                    .chain(),
+            );
+    }
+}
+
+// region: systems
+
+fn s_setup_debug_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font_config: Res<FontConfig>,
+) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(font_config.file.clone()),
+                    font_size: font_config.size / 2.,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_alignment(TextAlignment::Left),
+            text_anchor: bevy::sprite::Anchor::TopLeft,
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        TDebugText,
+    ));
+}
+
+fn s_debug_toggles(
+    keyboard: Res<Input<KeyCode>>,
+    mut overlay: ResMut<DebugOverlay>,
+    mut scale: ResMut<TimeScale>,
+) {
+    if keyboard.just_pressed(KeyCode::T) {
+        overlay.shapes = !overlay.shapes;
+    }
+    if keyboard.just_pressed(KeyCode::C) {
+        overlay.collision = !overlay.collision;
+    }
+    if keyboard.just_pressed(KeyCode::G) {
+        overlay.grid = !overlay.grid;
+    }
+    if keyboard.just_pressed(KeyCode::F2) {
+        let next = TIME_SCALES
+            .iter()
+            .position(|s| *s == scale.base)
+            .map_or(0, |i| (i + 1) % TIME_SCALES.len());
+        scale.base = TIME_SCALES[next];
+        info!("Time scale: {}", scale.base);
+    }
+}
+
+fn s_debug_draw(
+    overlay: Res<DebugOverlay>,
+    arena: Res<Arena>,
+    query: Query<(&CTransform, &CCollision), Without<TInactive>>,
+    mut gizmos: Gizmos,
+) {
+    if overlay.grid {
+        let color = Color::rgba(1., 1., 1., 0.2);
+        for x in (0..=arena.width as u32).step_by(64) {
+            let x = x as f32;
+            gizmos.line_2d(Vec2::new(x, 0.), Vec2::new(x, arena.height), color);
+        }
+        for y in (0..=arena.height as u32).step_by(64) {
+            let y = y as f32;
+            gizmos.line_2d(Vec2::new(0., y), Vec2::new(arena.width, y), color);
+        }
+    }
+
+    if overlay.collision {
+        for (tf, c) in query.iter() {
+            gizmos
+                .arc_2d(tf.pos, 0., 2. * PI, c.rad, Color::YELLOW)
+                .segments(32);
+            // where the entity will be a quarter second from now
+            gizmos.line_2d(tf.pos, tf.pos + tf.vel / 4., Color::CYAN);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_debug_text(
+    overlay: Res<DebugOverlay>,
+    arena: Res<Arena>,
+    diagnostics: Res<DiagnosticsStore>,
+    state: Res<State<AppState>>,
+    scale: Res<TimeScale>,
+    mut text: Query<(&mut Text, &mut Transform, &mut Visibility), With<TDebugText>>,
+    enemies: Query<(), With<TEnemy>>,
+    bullets: Query<(), (With<TBullet>, Without<TInactive>)>,
+    players: Query<(), With<TPlayer>>,
+    entities: Query<()>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = text.get_single_mut() else {
+        return;
+    };
+    if !overlay.grid {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+    transform.translation = Vec3::new(8., arena.height - 8., 1.);
+
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.);
+    text.sections[0].value = format!(
+        "FPS: {:.0}\nState: {:?}\nTime scale: {:.2}\nEntities: {}\nTPlayer: {}\nTEnemy: {}\nTBullet: {}",
+        fps,
+        state.get(),
+        scale.current,
+        entities.iter().count(),
+        players.iter().count(),
+        enemies.iter().count(),
+        bullets.iter().count(),
+    );
+}
+
+// endregion
@@ -0,0 +1,421 @@
+//! Headless test harness: builds the game with [`MinimalPlugins`], no window or renderer,
+//! configs injected as resources and input scripted per frame.
+
+// region: includes
+
+use bevy::{
+    input::mouse::MouseWheel, prelude::*, time::TimeUpdateStrategy, window::WindowResolution,
+};
+use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
+use comp4300_common::rng::RngSeed;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::*;
+use crate::pool::TInactive;
+use crate::{
+    boss::BossPlugin, net::NetPlugin, replay::ReplayPlugin, save::SavePlugin, stats::StatsPlugin,
+    telemetry::TelemetryPlugin, AppState, CBoundary, CCollision, CScore, CShape, CSlot, CTransform,
+    GamePlugin, Players, TEnemy, TPlayer,
+};
+
+// endregion
+
+// region: Harness Structs
+
+static NEXT_STATS_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// Every config the gameplay systems read, defaulting to the values shipped in `assets/config`
+/// except that enemies never spawn on their own, only the first bullet type exists and no
+/// achievements or bosses are set.
+pub struct Configs {
+    pub window: WindowConfig,
+    pub player: PlayerConfig,
+    pub enemy: EnemyConfig,
+    pub bullet: BulletConfig,
+    pub score: ScoreConfig,
+    /// networking stays off without one
+    pub net: Option<NetConfig>,
+    pub save: SaveConfig,
+    pub stats: StatsConfig,
+    pub boss: BossConfig,
+    pub focus: FocusConfig,
+    /// telemetry stays off without one
+    pub telemetry: Option<TelemetryConfig>,
+    /// no recording or playback without one
+    pub replay: Option<ReplayConfig>,
+    pub seed: u64,
+}
+
+impl Default for Configs {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig {
+                size: (1280., 720.),
+                frame_limit: 60.,
+                fullscreen: false,
+                renderer: Renderer::Gizmos,
+            },
+            player: PlayerConfig {
+                shape_radius: 32.,
+                collision_radius: 32.,
+                collision_mode: CollisionMode::Circle,
+                boundary: BoundaryMode::Clamp,
+                speed: 300.,
+                outline_color: (1., 1., 1.),
+                outline_thickness: 4.,
+                vertices: 8,
+                lives: 3,
+                count: 1,
+                slots: vec![
+                    PlayerSlot {
+                        color: (1., 0., 0.),
+                        controls: Controls::KeyboardMouse {
+                            up: KeyCode::W,
+                            down: KeyCode::S,
+                            left: KeyCode::A,
+                            right: KeyCode::D,
+                            prev_weapon: KeyCode::Q,
+                            next_weapon: KeyCode::E,
+                            focus: KeyCode::ShiftLeft,
+                        },
+                    },
+                    PlayerSlot {
+                        color: (0., 0.6, 1.),
+                        controls: Controls::Keyboard {
+                            up: KeyCode::Up,
+                            down: KeyCode::Down,
+                            left: KeyCode::Left,
+                            right: KeyCode::Right,
+                            shoot: KeyCode::ControlRight,
+                            prev_weapon: KeyCode::Comma,
+                            next_weapon: KeyCode::Period,
+                            focus: KeyCode::ShiftRight,
+                        },
+                    },
+                    PlayerSlot {
+                        color: (0.2, 1., 0.2),
+                        controls: Controls::Gamepad(0),
+                    },
+                    PlayerSlot {
+                        color: (1., 0.8, 0.),
+                        controls: Controls::Gamepad(1),
+                    },
+                ],
+            },
+            enemy: EnemyConfig {
+                shape_radius: 32.,
+                collision_radius: 32.,
+                collision_mode: CollisionMode::Polygon,
+                boundary: BoundaryMode::Bounce,
+                min_speed: -180.,
+                max_speed: 180.,
+                outline_color: (1., 1., 1.),
+                outline_thickness: 2.,
+                min_vertices: 3,
+                max_vertices: 8,
+                small_lifespan: 1.5,
+                small_speed: 60.,
+                small_boundary: BoundaryMode::Bounce,
+                small_pool_size: 256,
+                spawn_interval: f32::MAX,
+                min_spawn_distance: 200.,
+                telegraph_time: 0.75,
+                palette: Vec::new(),
+                fire_rate: 0.2,
+                projectile_speed: 220.,
+                accuracy: 0.8,
+                projectile: ProjectileType {
+                    shape_radius: 5.,
+                    collision_radius: 4.,
+                    color: (1., 0.5, 0.2),
+                    outline_color: (1., 1., 1.),
+                    outline_thickness: 1.,
+                    vertices: 6,
+                    lifespan: 3.,
+                },
+                difficulty: Difficulty::Normal,
+                crowding: Crowding::Overlap,
+            },
+            bullet: BulletConfig {
+                boundary: BoundaryMode::Despawn,
+                pool_size: 256,
+                types: vec![BulletType {
+                    name: "Pistol".to_string(),
+                    shape_radius: 10.,
+                    collision_radius: 10.,
+                    collision_mode: CollisionMode::Circle,
+                    speed: 600.,
+                    color: (1., 1., 1.),
+                    outline_color: (1., 0., 0.),
+                    outline_thickness: 2.,
+                    vertices: 20,
+                    lifespan: 1.5,
+                    count: 1,
+                    spread: 0.,
+                    pierce: 0,
+                    homing: 0.,
+                    boundary: None,
+                    bounces: None,
+                    fire_rate: 0.,
+                }],
+            },
+            score: ScoreConfig {
+                combo_step: 0.5,
+                max_multiplier: 5.,
+                combo_window: 1.5,
+                combo_decay: 1.,
+                popup_lifespan: 1.,
+                popup_speed: 60.,
+                popup_size: 24.,
+            },
+            net: None,
+            // out of the source tree, and apart for every test run
+            save: SaveConfig {
+                dir: std::env::temp_dir()
+                    .join(format!("comp4300-a2-saves-{}", std::process::id()))
+                    .to_string_lossy()
+                    .into_owned(),
+                slots: 3,
+            },
+            // a file of its own for every game, the stats carry over from one run to the next
+            stats: StatsConfig {
+                file: std::env::temp_dir()
+                    .join(format!(
+                        "comp4300-a2-stats-{}-{}.ron",
+                        std::process::id(),
+                        NEXT_STATS_FILE.fetch_add(1, Ordering::Relaxed)
+                    ))
+                    .to_string_lossy()
+                    .into_owned(),
+                toast_time: 4.,
+                achievements: Vec::new(),
+            },
+            boss: BossConfig {
+                wave_size: 20,
+                pause_spawns: true,
+                projectile: ProjectileType {
+                    shape_radius: 6.,
+                    collision_radius: 5.,
+                    color: (1., 0.3, 0.3),
+                    outline_color: (1., 1., 1.),
+                    outline_thickness: 1.,
+                    vertices: 8,
+                    lifespan: 4.,
+                },
+                bosses: Vec::new(),
+            },
+            focus: FocusConfig {
+                scale: 0.35,
+                transition: 0.25,
+                duration: 3.,
+                recharge: 8.,
+                min_start: 0.25,
+            },
+            telemetry: None,
+            replay: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Input held or given during one frame. Cursor positions are in window coordinates, with
+/// the origin in the top left like the real cursor, and clicks on consecutive frames hold the
+/// button down rather than clicking again.
+#[derive(Default)]
+pub struct FrameInput {
+    pub keys: Vec<KeyCode>,
+    pub click: Option<Vec2>,
+}
+
+pub struct TestApp {
+    pub app: App,
+    frame: usize,
+}
+
+// endregion
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::with_configs(Configs::default())
+    }
+
+    pub fn with_configs(configs: Configs) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            EntropyPlugin::<WyRand>::with_seed(configs.seed.to_le_bytes()),
+            NetPlugin,
+            SavePlugin,
+            StatsPlugin,
+            BossPlugin,
+            TelemetryPlugin,
+            ReplayPlugin,
+            GamePlugin,
+        ))
+        .init_resource::<Input<KeyCode>>()
+        .init_resource::<Input<MouseButton>>()
+        .init_resource::<Input<GamepadButton>>()
+        .init_resource::<Axis<GamepadAxis>>()
+        .add_event::<MouseWheel>()
+        .insert_resource(RngSeed(configs.seed))
+        .insert_resource(configs.player)
+        .insert_resource(configs.enemy)
+        .insert_resource(configs.bullet)
+        .insert_resource(configs.score)
+        .insert_resource(configs.save)
+        .insert_resource(configs.stats)
+        .insert_resource(configs.boss)
+        .insert_resource(configs.focus);
+        if let Some(net) = configs.net {
+            app.insert_resource(net);
+        }
+        if let Some(telemetry) = configs.telemetry {
+            app.insert_resource(telemetry);
+        }
+
+        // every update advances exactly one fixed tick
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+        app.world.spawn(Window {
+            resolution: WindowResolution::new(configs.window.size.0, configs.window.size.1),
+            ..Default::default()
+        });
+        app.insert_resource(configs.window);
+
+        if let Some(replay) = configs.replay {
+            app.insert_resource(replay);
+            // a replay starts at the end of a loading frame
+            app.update();
+        }
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+        // enter the game state and spawn the player
+        app.update();
+
+        Self { app, frame: 0 }
+    }
+
+    /// Runs one frame with nothing pressed.
+    pub fn step(&mut self) {
+        self.step_with(FrameInput::default());
+    }
+
+    pub fn step_with(&mut self, input: FrameInput) {
+        {
+            let mut keyboard = self.app.world.resource_mut::<Input<KeyCode>>();
+            keyboard.clear();
+            let held: Vec<KeyCode> = keyboard.get_pressed().copied().collect();
+            for key in held.into_iter().filter(|k| !input.keys.contains(k)) {
+                keyboard.release(key);
+            }
+            for key in input.keys.iter() {
+                keyboard.press(*key);
+            }
+        }
+        {
+            let mut mouse = self.app.world.resource_mut::<Input<MouseButton>>();
+            mouse.clear();
+            if input.click.is_some() {
+                mouse.press(MouseButton::Left);
+            } else {
+                mouse.release(MouseButton::Left);
+            }
+        }
+        if let Some(pos) = input.click {
+            let mut windows = self.app.world.query::<&mut Window>();
+            windows
+                .single_mut(&mut self.app.world)
+                .set_cursor_position(Some(pos));
+        }
+        self.app.update();
+        self.frame += 1;
+    }
+
+    pub fn run(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Runs `frames` frames, asking `script` for the input of each one by frame number.
+    pub fn run_script(&mut self, frames: usize, mut script: impl FnMut(usize) -> FrameInput) {
+        for _ in 0..frames {
+            let input = script(self.frame);
+            self.step_with(input);
+        }
+    }
+
+    pub fn count<T: Component>(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), (With<T>, Without<TInactive>)>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    /// Score of the first player.
+    pub fn score(&self) -> u32 {
+        self.score_of(0)
+    }
+
+    pub fn score_of(&self, slot: usize) -> u32 {
+        self.app.world.resource::<Players>().0[slot].score
+    }
+
+    pub fn lives_of(&self, slot: usize) -> u32 {
+        self.app.world.resource::<Players>().0[slot].lives
+    }
+
+    /// The only player, for games with one.
+    pub fn player(&mut self) -> Entity {
+        self.app
+            .world
+            .query_filtered::<Entity, With<TPlayer>>()
+            .single(&self.app.world)
+    }
+
+    /// The player in `slot`, if they are in the game right now.
+    pub fn player_in(&mut self, slot: usize) -> Option<Entity> {
+        self.app
+            .world
+            .query_filtered::<(Entity, &CSlot), With<TPlayer>>()
+            .iter(&self.app.world)
+            .find(|(_, s)| s.0 == slot)
+            .map(|(e, _)| e)
+    }
+
+    pub fn pos(&self, e: Entity) -> Vec2 {
+        self.app.world.get::<CTransform>(e).unwrap().pos
+    }
+
+    pub fn player_pos(&mut self) -> Vec2 {
+        let player = self.player();
+        self.pos(player)
+    }
+
+    /// Spawns a large enemy the same way the spawner does, at a chosen place and velocity.
+    pub fn spawn_enemy(&mut self, pos: Vec2, vel: Vec2, vertices: u32) -> Entity {
+        let enemy_config = self.app.world.resource::<EnemyConfig>();
+        let bundle = (
+            CTransform::new(pos, vel, 0.),
+            CShape {
+                radius: enemy_config.shape_radius,
+                color: Color::WHITE,
+                outline: Color::WHITE,
+                thickness: 2.,
+                vertices,
+            },
+            CCollision {
+                rad: enemy_config.collision_radius,
+                mode: enemy_config.collision_mode,
+            },
+            CScore(vertices * 100),
+            CBoundary(enemy_config.boundary),
+            TEnemy,
+        );
+        self.app.world.spawn(bundle).id()
+    }
+}
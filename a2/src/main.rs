@@ -4,13 +4,49 @@ use std::f32::consts::PI;
 
 use bevy::{prelude::*, window::*};
 use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand, resource::GlobalEntropy};
+use boss::{BossBarPlugin, BossPlugin, BossSchedule, CStruck};
+use collision::{bounce_apart, is_collision, push_apart};
+use comp4300_common::cli::{Cli, Flags, MaxFramesPlugin};
+use comp4300_common::rng::RandomExt;
+use comp4300_common::telemetry::CollisionCounts;
 use config::*;
-use rand_core::RngCore;
+use debug::{DebugOverlay, DebugPlugin};
+use mesh::ShapeMeshPlugin;
+use net::NetPlugin;
+use pool::{EntityPool, Pools, TInactive};
+use replay::ReplayPlugin;
+use save::SavePlugin;
+use serde::{Deserialize, Serialize};
+use settings::SettingsPlugin;
+use stats::{GameEvent, StatsPlugin, ToastPlugin};
+use telemetry::TelemetryPlugin;
 
+mod boss;
+mod collision;
 mod config;
+mod debug;
+#[cfg(test)]
+mod harness;
+mod mesh;
+mod net;
+mod pool;
+mod replay;
+mod save;
+mod settings;
+mod stats;
+mod telemetry;
+#[cfg(test)]
+mod tests;
 
 // endregion
 
+// random positions tried before an enemy settles for the one farthest from the player
+const SPAWN_ATTEMPTS: usize = 16;
+// columns and rows of the grid searched for a safe respawn point
+const SAFE_GRID: (usize, usize) = (8, 4);
+// how far a gamepad stick has to be pushed to move or aim
+const STICK_THRESHOLD: f32 = 0.5;
+
 // region: AppState
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
@@ -24,41 +60,114 @@ enum AppState {
 
 // region: Components
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CTransform {
     pos: Vec2,
+    // position before the last fixed tick, rendering interpolates between the two
+    prev_pos: Vec2,
+    // units per second
     vel: Vec2,
     angle: f32,
 }
 
-#[derive(Component)]
+impl CTransform {
+    fn new(pos: Vec2, vel: Vec2, angle: f32) -> Self {
+        Self {
+            pos,
+            prev_pos: pos,
+            vel,
+            angle,
+        }
+    }
+}
+
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CShape {
     radius: f32,
+    /// fill color, the outline too when drawn with gizmos
     color: Color,
+    outline: Color,
+    thickness: f32,
     vertices: u32,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CCollision {
     rad: f32,
+    mode: CollisionMode,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CScore(u32);
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CLifespan {
     remaining: f32,
     total: f32,
 }
 
-#[derive(Component, Default)]
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
 struct CInput {
     up: bool,
     left: bool,
     right: bool,
     down: bool,
+    // aim direction of a press not fired yet
     shoot: Option<Vec2>,
+    // aim direction while the fire button is held down
+    held: Option<Vec2>,
+    // last direction moved, players without a mouse or aiming stick fire this way
+    facing: Vec2,
+    // weapons to step forwards (or backwards when negative) on the next tick
+    cycle: i32,
+    // focus control held down, for slow motion
+    #[serde(default)]
+    focus: bool,
+}
+
+/// Index into `BulletConfig::types`.
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
+struct CWeapon(usize);
+
+/// Seconds left until the entity can fire again, players and enemies alike.
+#[derive(Component, Default, Clone, Serialize, Deserialize)]
+struct CCooldown(f32);
+
+#[derive(Component, Clone, Serialize, Deserialize)]
+struct CBoundary(BoundaryMode);
+
+/// A spawning enemy that doesn't move or collide until the time runs out.
+#[derive(Component, Clone, Serialize, Deserialize)]
+struct CTelegraph {
+    remaining: f32,
+    total: f32,
+}
+
+/// Enemies left to pass through.
+#[derive(Component, Clone, Serialize, Deserialize)]
+struct CPierce(u32);
+
+/// Turn rate towards the nearest enemy, in radians per second.
+#[derive(Component, Clone, Serialize, Deserialize)]
+struct CHoming(f32);
+
+/// Wall bounces left before despawning.
+#[derive(Component, Clone, Serialize, Deserialize)]
+struct CBounces(u32);
+
+/// Index into `PlayerConfig::slots` and `Players`.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+struct CSlot(usize);
+
+/// Player slot credited with the score of what this entity kills or is worth.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+struct COwner(usize);
+
+/// Bullets it takes to destroy the entity, for anything that takes more than one.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+struct CHealth {
+    current: u32,
+    max: u32,
 }
 
 // endregion
@@ -68,81 +177,241 @@ struct CInput {
 #[derive(Component)]
 struct TEnemy;
 
+/// Fired by a player. Bullets never collide with players, so co-op has no friendly fire.
 #[derive(Component)]
 struct TBullet;
 
 #[derive(Component)]
 struct TPlayer;
 
+/// A piece of a killed enemy or boss, harmless and gone when its lifespan runs out.
+#[derive(Component)]
+struct TFragment;
+
+/// Hurts players on contact without being destroyed by it: bosses and the projectiles they and
+/// enemies fire. Projectiles pass through bullets and enemies, so enemies never hit each other.
+#[derive(Component)]
+struct THostile;
+
+/// Floating "+N" text left where an enemy was killed.
+#[derive(Component)]
+struct TPopup(u32);
+
+/// HUD panel of one player slot.
+#[derive(Component)]
+struct TScoreText(usize);
+
 // endregion
 
 // region: resources
 
-#[derive(Resource)]
-struct TotalScore(u32);
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct PlayerState {
+    score: u32,
+    lives: u32,
+}
 
-#[derive(Resource)]
+/// Score and lives of every player slot in the game, indexed by `CSlot`.
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+struct Players(Vec<PlayerState>);
+
+#[derive(Resource, Clone, Serialize, Deserialize)]
 struct TimeSinceSpawn(f32);
 
+/// The playable area in world units, following the window size.
+#[derive(Resource, Default)]
+struct Arena {
+    width: f32,
+    height: f32,
+}
+
+/// Score multiplier raised by kills in quick succession.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+struct Combo {
+    multiplier: f32,
+    since_kill: f32,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.,
+            since_kill: 0.,
+        }
+    }
+}
+
+/// How fast the game runs, 1 being normal speed. Gameplay systems scale each fixed tick by
+/// `current`, which eases towards `target` so slow motion fades in and out.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+struct TimeScale {
+    current: f32,
+    target: f32,
+    /// the scale while nobody is focusing, stepped through with the debug key
+    base: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            current: 1.,
+            target: 1.,
+            base: 1.,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Seconds of game time in this fixed tick.
+    fn delta(&self, time: &Time<Fixed>) -> f32 {
+        time.delta_seconds() * self.current
+    }
+}
+
+/// The slow motion meter, shared by every player like the time scale it slows down.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+struct Focus {
+    /// fraction of the meter left
+    meter: f32,
+    active: bool,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Self {
+            meter: 1.,
+            active: false,
+        }
+    }
+}
+
 // endregion
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
-                primary_window: Some(Window {
-                    present_mode: PresentMode::AutoVsync,
-                    mode: WindowMode::Windowed,
-                    position: WindowPosition::Automatic,
-                    resolution: WindowResolution::new(800., 600.),
-                    title: "very cool game".to_string(),
-                    composite_alpha_mode: CompositeAlphaMode::Auto,
-                    resizable: false,
-                    enabled_buttons: EnabledButtons {
-                        minimize: true,
-                        maximize: false,
-                        close: true,
-                    },
-                    decorations: true,
-                    transparent: true,
-                    focused: true,
-                    window_level: WindowLevel::Normal,
-                    ..Default::default()
-                }),
-                ..Default::default()
-            }),
-            ConfigPlugin,
-            bevy_framepace::FramepacePlugin,
-            EntropyPlugin::<WyRand>::default(),
-        ))
-        .add_state::<AppState>()
-        .add_systems(OnEnter(AppState::InGame), (s_setup_window, s_setup_font))
+    let cli = Cli::from_env(
+        "a2",
+        Flags {
+            config_dir: true,
+            seed: true,
+            ..Default::default()
+        },
+    );
+    let mut app = App::new();
+    app.add_plugins((
+        cli.default_plugins(Window {
+            present_mode: PresentMode::AutoVsync,
+            mode: WindowMode::Windowed,
+            position: WindowPosition::Automatic,
+            resolution: WindowResolution::new(800., 600.),
+            title: "very cool game".to_string(),
+            composite_alpha_mode: CompositeAlphaMode::Auto,
+            resizable: true,
+            enabled_buttons: EnabledButtons {
+                minimize: true,
+                maximize: true,
+                close: true,
+            },
+            decorations: true,
+            transparent: true,
+            focused: true,
+            window_level: WindowLevel::Normal,
+            ..Default::default()
+        }),
+        ConfigPlugin,
+        EntropyPlugin::<WyRand>::default(),
+        ReplayPlugin,
+        NetPlugin,
+        SavePlugin,
+        (StatsPlugin, ToastPlugin),
+        (BossPlugin, BossBarPlugin),
+        DebugPlugin,
+        SettingsPlugin,
+        TelemetryPlugin,
+        ShapeMeshPlugin,
+        GamePlugin,
+        MaxFramesPlugin(cli.max_frames),
+    ));
+    if cli.headless {
+        // the schedule runner paces headless runs, the settings stay for the systems setting them
+        app.init_resource::<bevy_framepace::FramepaceSettings>();
+    } else {
+        app.add_plugins(bevy_framepace::FramepacePlugin);
+    }
+    app.insert_resource(cli)
+        .add_systems(
+            OnEnter(AppState::InGame),
+            (s_setup_window.before(s_setup_arena), s_setup_font),
+        )
         .add_systems(
             Update,
-            (
-                s_collisions,
-                s_render,
-                s_input,
-                s_enemy_spawner,
-                s_lifespan,
-                s_movement.after(s_input),
-            )
-                .run_if(in_state(AppState::InGame)),
+            (s_render, s_popup_text).run_if(in_state(AppState::InGame)),
         )
-        .insert_resource(TimeSinceSpawn(0.))
-        .insert_resource(TotalScore(0))
         .run();
 }
 
+/// The game's state, gameplay systems and resources, without any window or rendering so it
+/// can also run headless.
+struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    s_setup_pools,
+                    s_setup_arena.before(s_setup_player),
+                    s_setup_player.run_if(net::simulates),
+                ),
+            )
+            // read before the fixed ticks of the same frame so input isn't a frame late
+            .add_systems(
+                PreUpdate,
+                s_input
+                    .after(bevy::input::InputSystem)
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(settings::closed),
+            )
+            .add_systems(
+                FixedUpdate,
+                // chained so a recorded run replays in exactly the same order
+                (
+                    s_focus,
+                    s_arena,
+                    s_shoot,
+                    s_enemy_shoot,
+                    s_homing,
+                    s_movement,
+                    s_crowding,
+                    s_popups,
+                    s_collisions,
+                    s_combo,
+                    s_lifespan,
+                    s_enemy_spawner,
+                    s_telegraph,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(net::simulates),
+            )
+            .insert_resource(Time::<Fixed>::from_hz(60.))
+            .insert_resource(TimeSinceSpawn(0.))
+            .init_resource::<Players>()
+            .init_resource::<Arena>()
+            .init_resource::<Combo>()
+            .init_resource::<TimeScale>()
+            .init_resource::<Focus>()
+            .init_resource::<CollisionCounts>();
+    }
+}
+
 // region: setup systems
 
 fn s_setup_window(
     mut commands: Commands,
     mut framepace_settings: ResMut<bevy_framepace::FramepaceSettings>,
     mut windows: Query<&mut Window>,
-    player_config: Res<PlayerConfig>,
     window_config: Res<WindowConfig>,
-    mut total_score: ResMut<TotalScore>,
 ) {
     commands.spawn(Camera2dBundle {
         projection: OrthographicProjection {
@@ -163,299 +432,929 @@ fn s_setup_window(
     if window_config.fullscreen {
         window.mode = WindowMode::BorderlessFullscreen
     }
+}
 
-    let width = window.resolution.width();
-    let height = window.resolution.height();
+fn s_setup_pools(
+    mut commands: Commands,
+    bullet_config: Res<BulletConfig>,
+    enemy_config: Res<EnemyConfig>,
+) {
+    commands.insert_resource(Pools {
+        bullets: EntityPool::new(bullet_config.pool_size),
+        fragments: EntityPool::new(enemy_config.small_pool_size),
+    });
+}
+
+fn s_setup_arena(mut arena: ResMut<Arena>, windows: Query<&Window>) {
+    let window = windows.single();
+    arena.width = window.width();
+    arena.height = window.height();
+}
 
-    spawn_player(
-        &mut commands,
-        &player_config,
-        &mut total_score,
-        &width,
-        &height,
-    )
+fn s_setup_player(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    player_config: Res<PlayerConfig>,
+    mut players: ResMut<Players>,
+) {
+    spawn_players(&mut commands, &player_config, &arena, &mut players);
 }
 
 fn s_setup_font(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     font_config: Res<FontConfig>,
+    player_config: Res<PlayerConfig>,
 ) {
-    commands.spawn(Text2dBundle {
-        text: Text::from_section(
-            "0",
-            TextStyle {
-                font: asset_server.load(font_config.file.clone()),
-                font_size: font_config.size,
-                color: Color::rgba(
-                    font_config.color.0,
-                    font_config.color.1,
-                    font_config.color.2,
-                    1.,
-                ),
-            },
-        )
-        .with_alignment(TextAlignment::Left),
+    for slot in 0..player_config.players() {
+        let color = player_config.slots[slot].color;
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    "0",
+                    TextStyle {
+                        font: asset_server.load(font_config.file.clone()),
+                        font_size: font_config.size,
+                        color: Color::rgba(color.0, color.1, color.2, 1.),
+                    },
+                )
+                .with_alignment(TextAlignment::Left),
 
-        transform: Transform {
-            translation: Vec3::new(20., 20., 0.),
+                transform: Transform {
+                    translation: Vec3::new(20., 20., 0.),
+                    ..Default::default()
+                },
+                text_anchor: bevy::sprite::Anchor::BottomLeft,
+                ..Default::default()
+            })
+            .insert(TScoreText(slot));
+    }
+}
+
+fn s_popup_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font_config: Res<FontConfig>,
+    score_config: Res<ScoreConfig>,
+    new_popups: Query<(Entity, &TPopup), Added<TPopup>>,
+    mut popups: Query<(&CTransform, &CLifespan, &mut Transform, &mut Text), With<TPopup>>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    for (e, popup) in new_popups.iter() {
+        commands.entity(e).insert(Text2dBundle {
+            text: Text::from_section(
+                format!("+{}", popup.0),
+                TextStyle {
+                    font: asset_server.load(font_config.file.clone()),
+                    font_size: score_config.popup_size,
+                    color: Color::rgb(
+                        font_config.color.0,
+                        font_config.color.1,
+                        font_config.color.2,
+                    ),
+                },
+            ),
             ..Default::default()
-        },
-        text_anchor: bevy::sprite::Anchor::BottomLeft,
-        ..Default::default()
-    });
+        });
+    }
+
+    let alpha = fixed_time.overstep_percentage();
+    for (tf, ls, mut transform, mut text) in popups.iter_mut() {
+        transform.translation = tf.prev_pos.lerp(tf.pos, alpha).extend(1.);
+        text.sections[0].style.color.set_a(ls.remaining / ls.total);
+    }
 }
 
 // endregion
 
 // region: systems
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn s_render(
-    circle_query: Query<(&CShape, &CTransform), Without<CLifespan>>,
-    mut lifespan_query: Query<(&mut CShape, &CTransform, &CLifespan)>,
-    mut text_query: Query<&mut Text>,
+    circle_query: Query<
+        (&CShape, &CTransform),
+        (Without<CLifespan>, Without<CTelegraph>, Without<TInactive>),
+    >,
+    telegraph_query: Query<(&CShape, &CTransform, &CTelegraph)>,
+    mut lifespan_query: Query<(&mut CShape, &CTransform, &CLifespan), Without<TInactive>>,
+    mut text_query: Query<(&mut Text, &mut Transform, &TScoreText)>,
     mut gizmos: Gizmos,
-    total_score: Res<TotalScore>,
+    players: Res<Players>,
+    combo: Res<Combo>,
+    focus: Res<Focus>,
+    weapons: Query<(&CSlot, &CWeapon)>,
+    bullet_config: Res<BulletConfig>,
+    fixed_time: Res<Time<Fixed>>,
+    overlay: Res<DebugOverlay>,
+    arena: Res<Arena>,
+    window_config: Res<WindowConfig>,
 ) {
-    for mut text in text_query.iter_mut() {
-        text.sections[0].value = format!("Score: {}", total_score.0);
+    // panels share the bottom of the window evenly
+    let panel_width = arena.width / players.0.len().max(1) as f32;
+    for (mut text, mut transform, panel) in text_query.iter_mut() {
+        let Some(state) = players.0.get(panel.0) else {
+            continue;
+        };
+        let weapon = weapons
+            .iter()
+            .find(|(slot, _)| slot.0 == panel.0)
+            .and_then(|(_, w)| bullet_config.types.get(w.0))
+            .map_or("", |b| b.name.as_str());
+        text.sections[0].value = format!(
+            "P{}  Score: {}  Lives: {}  x{:.1}  {}  Focus: {:.0}%",
+            panel.0 + 1,
+            state.score,
+            state.lives,
+            combo.multiplier,
+            weapon,
+            focus.meter * 100.
+        );
+        transform.translation.x = 20. + panel_width * panel.0 as f32;
+    }
+
+    if !overlay.shapes || window_config.renderer != Renderer::Gizmos {
+        return;
     }
 
+    // how far we are between the last fixed tick and the next one
+    let alpha = fixed_time.overstep_percentage();
+
     for (shape, tf) in circle_query.iter() {
         gizmos
-            .arc_2d(tf.pos, tf.angle, 2. * PI, shape.radius, shape.color)
+            .arc_2d(
+                tf.prev_pos.lerp(tf.pos, alpha),
+                tf.angle,
+                2. * PI,
+                shape.radius,
+                shape.color,
+            )
+            .segments(shape.vertices as usize);
+    }
+
+    // outlines fade in where enemies are about to appear
+    for (shape, tf, telegraph) in telegraph_query.iter() {
+        let color = shape
+            .color
+            .with_a(1. - telegraph.remaining / telegraph.total);
+        gizmos
+            .arc_2d(tf.pos, tf.angle, 2. * PI, shape.radius, color)
             .segments(shape.vertices as usize);
     }
 
     for (mut shape, tf, ls) in lifespan_query.iter_mut() {
         shape.color.set_a(ls.remaining / ls.total);
         gizmos
-            .arc_2d(tf.pos, tf.angle, 2. * PI, shape.radius, shape.color)
+            .arc_2d(
+                tf.prev_pos.lerp(tf.pos, alpha),
+                tf.angle,
+                2. * PI,
+                shape.radius,
+                shape.color,
+            )
             .segments(shape.vertices as usize);
     }
 }
 
-fn s_movement(
+/// Follows the window size, pulling entities left outside a shrunken arena back in.
+fn s_arena(
+    mut arena: ResMut<Arena>,
+    windows: Query<&Window>,
+    mut query: Query<(&CShape, &mut CTransform), Without<TInactive>>,
+) {
+    let window = windows.single();
+    if arena.width == window.width() && arena.height == window.height() {
+        return;
+    }
+    arena.width = window.width();
+    arena.height = window.height();
+    for (shape, mut tf) in query.iter_mut() {
+        let pos = tf.pos.max(Vec2::splat(shape.radius)).min(Vec2::new(
+            arena.width - shape.radius,
+            arena.height - shape.radius,
+        ));
+        if pos != tf.pos {
+            tf.pos = pos;
+            tf.prev_pos = pos;
+        }
+    }
+}
+
+fn s_shoot(
     mut commands: Commands,
-    mut circle_query: Query<(&CShape, &mut CTransform), Without<CInput>>,
-    mut input_query: Query<(&CShape, &mut CInput, &mut CTransform)>,
-    window: Query<&Window>,
-    time: Res<Time>,
-    player_config: Res<PlayerConfig>,
+    mut query: Query<(
+        &CSlot,
+        &mut CInput,
+        &mut CWeapon,
+        &mut CCooldown,
+        &CTransform,
+    )>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
     bullet_config: Res<BulletConfig>,
+    mut pools: ResMut<Pools>,
+    mut events: EventWriter<GameEvent>,
 ) {
-    let window = window.single();
-    let width = window.resolution.width();
-    let height = window.resolution.height();
-
-    match input_query.get_single_mut() {
-        Ok((shape, input, mut tf)) => {
-            tf.vel = Vec2::ZERO;
-            if input.up {
-                tf.vel.y = 1.;
-            }
-            if input.down {
-                tf.vel.y = -1.
-            }
-            if input.left {
-                tf.vel.x = -1.
-            }
-            if input.right {
-                tf.vel.x = 1.
-            }
+    let weapons = bullet_config.types.len();
+    for (slot, mut input, mut weapon, mut cooldown, tf) in query.iter_mut() {
+        if input.cycle != 0 && weapons > 0 {
+            weapon.0 = (weapon.0 as i32 + input.cycle).rem_euclid(weapons as i32) as usize;
+            cooldown.0 = 0.;
+        }
+        input.cycle = 0;
+        cooldown.0 -= scale.delta(&time);
 
-            if let Some(mut mouse_pos) = input.shoot {
-                // convert from window coords to world space
-                mouse_pos = Vec2::new(mouse_pos.x, (height - mouse_pos.y).abs());
-                commands.spawn((
-                    CShape {
-                        radius: bullet_config.shape_radius,
-                        color: Color::rgba(
-                            bullet_config.color.0,
-                            bullet_config.color.1,
-                            bullet_config.color.2,
-                            1.,
-                        ),
-                        vertices: bullet_config.vertices,
-                    },
-                    CTransform {
-                        pos: tf.pos,
-                        vel: (mouse_pos - tf.pos).normalize() * bullet_config.speed,
-                        angle: 0.,
-                    },
-                    CLifespan {
-                        remaining: bullet_config.lifespan,
-                        total: bullet_config.lifespan,
-                    },
-                    CCollision {
-                        rad: bullet_config.collision_radius,
-                    },
-                    TBullet,
-                ));
-            }
+        let Some(bullet) = bullet_config.types.get(weapon.0) else {
+            continue;
+        };
+        // holding only repeats fire for weapons with a rate, unlimited ones fire once per click
+        let held = input.held.filter(|_| bullet.fire_rate > 0.);
+        let Some(aim) = input.shoot.take().or(held) else {
+            continue;
+        };
+        if cooldown.0 > 0. {
+            continue;
+        }
+        if bullet.fire_rate > 0. {
+            // carry the leftover time over so held fire keeps an exact rate
+            cooldown.0 = cooldown.0.max(-scale.delta(&time)) + 1. / bullet.fire_rate;
+        }
+        let boundary = bullet.boundary.unwrap_or(bullet_config.boundary);
+        let bullets = spawn_bullets(
+            &mut commands,
+            &mut pools.bullets,
+            tf.pos,
+            aim,
+            bullet,
+            boundary,
+            *slot,
+        );
+        events.send_batch(bullets.into_iter().map(|bullet| GameEvent::Shot {
+            slot: slot.0,
+            bullet,
+        }));
+    }
+}
 
-            tf.vel = tf.vel.normalize_or_zero();
-            tf.vel = tf.vel * player_config.speed;
-            transform_tick(
-                tf.as_mut(),
-                shape.radius,
-                width,
-                height,
-                time.delta_seconds(),
-            );
+/// Fires every enemy's projectiles at the nearest player, off by up to 90 degrees at an
+/// accuracy of 0.
+#[allow(clippy::type_complexity)]
+fn s_enemy_shoot(
+    mut commands: Commands,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut enemies: Query<(&CTransform, &mut CCooldown), (With<TEnemy>, Without<CTelegraph>)>,
+    players: Query<&CTransform, With<TPlayer>>,
+    enemy_config: Res<EnemyConfig>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
+) {
+    let rate = enemy_config.fire_rate * enemy_config.difficulty.fire_rate_scale();
+    if rate <= 0. {
+        return;
+    }
+    let max_error = (1. - enemy_config.accuracy).clamp(0., 1.) * PI / 2.;
+    for (tf, mut cooldown) in enemies.iter_mut() {
+        cooldown.0 -= scale.delta(&time);
+        if cooldown.0 > 0. {
+            continue;
         }
-        Err(err) => {
-            error!("main.rs::movement_system: {}", err)
+        cooldown.0 = cooldown.0.max(-scale.delta(&time)) + 1. / rate;
+        let Some(target) = players
+            .iter()
+            .map(|p| p.pos)
+            .min_by(|a, b| a.distance(tf.pos).total_cmp(&b.distance(tf.pos)))
+        else {
+            continue;
+        };
+        let Some(aim) = (target - tf.pos).try_normalize() else {
+            continue;
+        };
+        let error = if max_error > 0. {
+            rng.range_f32(-max_error, max_error)
+        } else {
+            0.
+        };
+        spawn_projectile(
+            &mut commands,
+            tf.pos,
+            Vec2::from_angle(error).rotate(aim) * enemy_config.projectile_speed,
+            &enemy_config.projectile,
+        );
+    }
+}
+
+/// Steers homing bullets towards the nearest enemy, keeping their speed.
+#[allow(clippy::type_complexity)]
+fn s_homing(
+    mut bullets: Query<(&CHoming, &mut CTransform), Without<TInactive>>,
+    enemies: Query<&CTransform, (With<TEnemy>, Without<CHoming>, Without<CTelegraph>)>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
+) {
+    for (homing, mut tf) in bullets.iter_mut() {
+        let Some(target) = enemies.iter().map(|e_tf| e_tf.pos).min_by(|a, b| {
+            a.distance_squared(tf.pos)
+                .total_cmp(&b.distance_squared(tf.pos))
+        }) else {
+            continue;
+        };
+        let max_turn = homing.0 * scale.delta(&time);
+        let turn = tf.vel.angle_between(target - tf.pos);
+        if turn.is_finite() {
+            tf.vel = Vec2::from_angle(turn.clamp(-max_turn, max_turn)).rotate(tf.vel);
         }
     }
+}
 
-    for (shape, mut tf) in circle_query.iter_mut() {
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn s_movement(
+    mut commands: Commands,
+    mut pools: ResMut<Pools>,
+    mut circle_query: Query<
+        (
+            Entity,
+            &CShape,
+            &CBoundary,
+            &mut CTransform,
+            Option<&mut CBounces>,
+        ),
+        (Without<CInput>, Without<CTelegraph>, Without<TInactive>),
+    >,
+    mut input_query: Query<(&CShape, &CBoundary, &CInput, &mut CTransform)>,
+    arena: Res<Arena>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
+    player_config: Res<PlayerConfig>,
+) {
+    let width = arena.width;
+    let height = arena.height;
+
+    for (shape, boundary, input, mut tf) in input_query.iter_mut() {
+        tf.vel = Vec2::ZERO;
+        if input.up {
+            tf.vel.y = 1.;
+        }
+        if input.down {
+            tf.vel.y = -1.
+        }
+        if input.left {
+            tf.vel.x = -1.
+        }
+        if input.right {
+            tf.vel.x = 1.
+        }
+
+        tf.vel = tf.vel.normalize_or_zero();
+        tf.vel *= player_config.speed;
+        let boundary = match boundary.0 {
+            BoundaryMode::Despawn => BoundaryMode::Clamp,
+            mode => mode,
+        };
         transform_tick(
             tf.as_mut(),
             shape.radius,
+            boundary,
+            width,
+            height,
+            scale.delta(&time),
+        );
+    }
+
+    for (e, shape, boundary, mut tf, bounces) in circle_query.iter_mut() {
+        let at_edge = transform_tick(
+            tf.as_mut(),
+            shape.radius,
+            boundary.0,
             width,
             height,
-            time.delta_seconds(),
+            scale.delta(&time),
         );
+        if !at_edge {
+            continue;
+        }
+        match (boundary.0, bounces) {
+            (BoundaryMode::Despawn, _) => pools.release(&mut commands, e),
+            (BoundaryMode::Bounce, Some(mut bounces)) => {
+                if bounces.0 == 0 {
+                    pools.release(&mut commands, e);
+                } else {
+                    bounces.0 -= 1;
+                }
+            }
+            _ => (),
+        }
     }
 }
 
+/// With `Crowding::Bounce`, enemies bounce off each other by their collision radius, weighted
+/// by their vertex count, and fragments push each other apart instead of stacking up.
+#[allow(clippy::type_complexity)]
+fn s_crowding(
+    mut enemy_query: Query<
+        (&CCollision, &CShape, &CBoundary, &mut CTransform),
+        (With<TEnemy>, Without<CTelegraph>),
+    >,
+    mut fragment_query: Query<
+        (&CShape, &mut CTransform),
+        (With<TFragment>, Without<TEnemy>, Without<TInactive>),
+    >,
+    mut collisions: ResMut<CollisionCounts>,
+    arena: Res<Arena>,
+    enemy_config: Res<EnemyConfig>,
+) {
+    if enemy_config.crowding != Crowding::Bounce {
+        return;
+    }
+
+    let mut pairs = enemy_query.iter_combinations_mut();
+    while let Some([(c1, s1, _, mut tf1), (c2, s2, _, mut tf2)]) = pairs.fetch_next() {
+        collisions.record(bounce_apart(
+            tf1.as_mut(),
+            c1.rad,
+            s1.vertices.max(1) as f32,
+            tf2.as_mut(),
+            c2.rad,
+            s2.vertices.max(1) as f32,
+        ));
+    }
+    // a push must not leave an enemy past an edge it is meant to stay inside
+    for (_, shape, boundary, mut tf) in enemy_query.iter_mut() {
+        if matches!(boundary.0, BoundaryMode::Bounce | BoundaryMode::Clamp) {
+            let min = Vec2::splat(shape.radius);
+            let max = Vec2::new(arena.width, arena.height) - shape.radius;
+            let pos = tf.pos.clamp(min, max.max(min));
+            if pos != tf.pos {
+                tf.pos = pos;
+            }
+        }
+    }
+
+    let mut pairs = fragment_query.iter_combinations_mut();
+    while let Some([(s1, mut tf1), (s2, mut tf2)]) = pairs.fetch_next() {
+        collisions.record(push_apart(tf1.as_mut(), s1.radius, tf2.as_mut(), s2.radius));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn s_input(
     keyboard: Res<Input<KeyCode>>,
     mouse: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut wheel: EventReader<bevy::input::mouse::MouseWheel>,
     mut app_exit_events: ResMut<Events<bevy::app::AppExit>>,
-    mut input: Query<&mut CInput>,
+    mut query: Query<(&CSlot, &CTransform, &mut CInput)>,
     window: Query<&Window>,
+    player_config: Res<PlayerConfig>,
 ) {
-    match input.get_single_mut() {
-        Ok(mut input) => {
-            input.up = keyboard.pressed(KeyCode::W);
-            input.down = keyboard.pressed(KeyCode::S);
-            input.left = keyboard.pressed(KeyCode::A);
-            input.right = keyboard.pressed(KeyCode::D);
-            if mouse.just_pressed(MouseButton::Left) {
-                input.shoot = window.single().cursor_position();
-            } else {
-                input.shoot = None;
+    let window = window.single();
+    // convert from window coords to world space
+    let cursor = window
+        .cursor_position()
+        .map(|pos| Vec2::new(pos.x, window.height() - pos.y));
+    let scroll: i32 = wheel.read().map(|event| event.y.signum() as i32).sum();
+    for (slot, tf, mut input) in query.iter_mut() {
+        let Some(player_slot) = player_config.slots.get(slot.0) else {
+            error!("main.rs::s_input: no config for player slot {}", slot.0);
+            continue;
+        };
+        let (aim, fire_pressed, fire_held) = match player_slot.controls {
+            Controls::KeyboardMouse {
+                up,
+                down,
+                left,
+                right,
+                prev_weapon,
+                next_weapon,
+                focus,
+            } => {
+                input.up = keyboard.pressed(up);
+                input.down = keyboard.pressed(down);
+                input.left = keyboard.pressed(left);
+                input.right = keyboard.pressed(right);
+                input.focus = keyboard.pressed(focus);
+                input.cycle += keyboard.just_pressed(next_weapon) as i32
+                    - keyboard.just_pressed(prev_weapon) as i32
+                    + scroll;
+                (
+                    cursor.map(|pos| pos - tf.pos),
+                    mouse.just_pressed(MouseButton::Left),
+                    mouse.pressed(MouseButton::Left),
+                )
+            }
+            Controls::Keyboard {
+                up,
+                down,
+                left,
+                right,
+                shoot,
+                prev_weapon,
+                next_weapon,
+                focus,
+            } => {
+                input.up = keyboard.pressed(up);
+                input.down = keyboard.pressed(down);
+                input.left = keyboard.pressed(left);
+                input.right = keyboard.pressed(right);
+                input.focus = keyboard.pressed(focus);
+                input.cycle += keyboard.just_pressed(next_weapon) as i32
+                    - keyboard.just_pressed(prev_weapon) as i32;
+                (None, keyboard.just_pressed(shoot), keyboard.pressed(shoot))
+            }
+            Controls::Gamepad(id) => {
+                let gamepad = Gamepad::new(id);
+                let axis = |axis_type| {
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, axis_type))
+                        .unwrap_or(0.)
+                };
+                let button = |button_type| GamepadButton::new(gamepad, button_type);
+                let stick = Vec2::new(
+                    axis(GamepadAxisType::LeftStickX),
+                    axis(GamepadAxisType::LeftStickY),
+                );
+                input.up = stick.y > STICK_THRESHOLD;
+                input.down = stick.y < -STICK_THRESHOLD;
+                input.left = stick.x < -STICK_THRESHOLD;
+                input.right = stick.x > STICK_THRESHOLD;
+                input.focus = gamepad_buttons.pressed(button(GamepadButtonType::LeftTrigger2));
+                input.cycle += gamepad_buttons.just_pressed(button(GamepadButtonType::RightTrigger))
+                    as i32
+                    - gamepad_buttons.just_pressed(button(GamepadButtonType::LeftTrigger)) as i32;
+                let aim = Vec2::new(
+                    axis(GamepadAxisType::RightStickX),
+                    axis(GamepadAxisType::RightStickY),
+                );
+                (
+                    (aim.length() > STICK_THRESHOLD).then_some(aim),
+                    gamepad_buttons.just_pressed(button(GamepadButtonType::RightTrigger2)),
+                    gamepad_buttons.pressed(button(GamepadButtonType::RightTrigger2)),
+                )
             }
+        };
+
+        let moving = Vec2::new(
+            (input.right as i32 - input.left as i32) as f32,
+            (input.up as i32 - input.down as i32) as f32,
+        );
+        if moving != Vec2::ZERO {
+            input.facing = moving.normalize();
         }
-        Err(err) => error!("main.rs::keyboard_system: {}", err),
+        let aim = aim.unwrap_or(input.facing);
+        // kept until the next fixed tick fires it, so presses between ticks aren't lost
+        if fire_pressed {
+            input.shoot = Some(aim);
+        }
+        input.held = fire_held.then_some(aim);
     }
     if keyboard.pressed(KeyCode::Escape) {
         app_exit_events.send(bevy::app::AppExit)
     }
 }
 
+/// Drains the focus meter while a player holds focus and refills it otherwise, then eases the
+/// time scale towards the speed that asks for. The meter runs on unscaled time, so slow motion
+/// doesn't stretch itself out.
+fn s_focus(
+    mut focus: ResMut<Focus>,
+    mut scale: ResMut<TimeScale>,
+    inputs: Query<&CInput, With<TPlayer>>,
+    focus_config: Res<FocusConfig>,
+    time: Res<Time<Fixed>>,
+) {
+    let held = inputs.iter().any(|input| input.focus);
+    // starting takes part of the meter, so holding focus on an empty one doesn't flicker
+    focus.active =
+        held && focus.meter > 0. && (focus.active || focus.meter >= focus_config.min_start);
+    focus.meter = if focus.active {
+        (focus.meter - time.delta_seconds() / focus_config.duration).max(0.)
+    } else {
+        (focus.meter + time.delta_seconds() / focus_config.recharge).min(1.)
+    };
+
+    scale.target = scale.base * if focus.active { focus_config.scale } else { 1. };
+    // at the rate that goes from 1 to the focus scale in `transition` seconds, 0 jumps there
+    let rate = (1. - focus_config.scale).abs().max(f32::EPSILON) / focus_config.transition.max(0.);
+    let step = rate * time.delta_seconds();
+    scale.current += (scale.target - scale.current).clamp(-step, step);
+}
+
+#[allow(clippy::too_many_arguments)]
 fn s_enemy_spawner(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
     mut rng: ResMut<GlobalEntropy<WyRand>>,
     mut time_since_spawn: ResMut<TimeSinceSpawn>,
     enemy_config: Res<EnemyConfig>,
-    windows: Query<&Window>,
+    arena: Res<Arena>,
+    players: Query<&CTransform, With<TPlayer>>,
+    boss_config: Res<BossConfig>,
+    mut boss_schedule: ResMut<BossSchedule>,
+    bosses: Query<(), With<CHealth>>,
 ) {
-    time_since_spawn.0 += time.delta_seconds();
+    if boss_config.pause_spawns && !bosses.is_empty() {
+        return;
+    }
+    time_since_spawn.0 += scale.delta(&time);
     if time_since_spawn.0 > enemy_config.spawn_interval {
         time_since_spawn.0 = 0.;
-        let window = windows.single();
-        let vertices = rng_range_u32(
-            rng.as_mut(),
-            enemy_config.min_vertices,
-            enemy_config.max_vertices,
-        );
-        commands.spawn((
-            CTransform {
-                pos: Vec2 {
-                    x: rng_range(
-                        rng.as_mut(),
-                        enemy_config.shape_radius,
-                        window.width() - enemy_config.shape_radius,
-                    ),
-                    y: rng_range(
-                        rng.as_mut(),
-                        enemy_config.shape_radius,
-                        window.height() - enemy_config.shape_radius,
-                    ),
-                },
-                vel: Vec2 {
-                    x: rng_range(rng.as_mut(), enemy_config.min_speed, enemy_config.max_speed),
-                    y: rng_range(rng.as_mut(), enemy_config.min_speed, enemy_config.max_speed),
+        boss_schedule.spawned += 1;
+        let vertices = rng.range_u32(enemy_config.min_vertices, enemy_config.max_vertices);
+
+        // retry a few times for a spot away from every player, keeping the farthest one in
+        // case the arena is too small to have any
+        let mut pos = Vec2::ZERO;
+        let mut best_distance = f32::MIN;
+        for _ in 0..SPAWN_ATTEMPTS {
+            let candidate = Vec2 {
+                x: rng.range_f32(
+                    enemy_config.shape_radius,
+                    arena.width - enemy_config.shape_radius,
+                ),
+                y: rng.range_f32(
+                    enemy_config.shape_radius,
+                    arena.height - enemy_config.shape_radius,
+                ),
+            };
+            let distance = players
+                .iter()
+                .map(|p_tf| p_tf.pos.distance(candidate))
+                .fold(f32::MAX, f32::min);
+            if distance > best_distance {
+                pos = candidate;
+                best_distance = distance;
+            }
+            if distance >= enemy_config.min_spawn_distance {
+                break;
+            }
+        }
+
+        let mut enemy = commands.spawn((
+            CTransform::new(
+                pos,
+                Vec2 {
+                    x: rng.range_f32(enemy_config.min_speed, enemy_config.max_speed),
+                    y: rng.range_f32(enemy_config.min_speed, enemy_config.max_speed),
                 },
-                angle: 0.,
-            },
+                0.,
+            ),
             CShape {
                 radius: enemy_config.shape_radius,
-                color: Color::rgba(
-                    rng_range(rng.as_mut(), 0.2, 1.),
-                    rng_range(rng.as_mut(), 0.2, 1.),
-                    rng_range(rng.as_mut(), 0.2, 1.),
-                    1.,
-                ),
+                color: rng.palette_color(&enemy_config.palette),
+                outline: rgb(enemy_config.outline_color),
+                thickness: enemy_config.outline_thickness,
                 vertices,
             },
             CCollision {
                 rad: enemy_config.collision_radius,
+                mode: enemy_config.collision_mode,
             },
             CScore(vertices * 100),
+            CBoundary(enemy_config.boundary),
             TEnemy,
         ));
+        if enemy_config.telegraph_time > 0. {
+            enemy.insert(CTelegraph {
+                remaining: enemy_config.telegraph_time,
+                total: enemy_config.telegraph_time,
+            });
+        }
+        // enemies that appear together don't fire together
+        let rate = enemy_config.fire_rate * enemy_config.difficulty.fire_rate_scale();
+        if rate > 0. {
+            enemy.insert(CCooldown(rng.range_f32(0., 1. / rate)));
+        }
     }
 }
 
+fn s_telegraph(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut CTelegraph)>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
+) {
+    for (e, mut telegraph) in query.iter_mut() {
+        telegraph.remaining -= scale.delta(&time);
+        if telegraph.remaining <= 0. {
+            commands.entity(e).remove::<CTelegraph>();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn s_collisions(
     mut commands: Commands,
-    mut total_score: ResMut<TotalScore>,
-    bullet_query: Query<(Entity, &CTransform, &CCollision, &TBullet)>,
-    enemy_query: Query<(Entity, &CTransform, &CCollision, &CShape, &CScore, &TEnemy)>,
-    mut player: Query<(Entity, &CTransform, &CCollision, &TPlayer)>,
+    mut players: ResMut<Players>,
+    mut combo: ResMut<Combo>,
+    mut pools: ResMut<Pools>,
+    mut bullet_query: Query<
+        (
+            Entity,
+            &CTransform,
+            &CCollision,
+            &CShape,
+            &COwner,
+            Option<&mut CPierce>,
+            &TBullet,
+        ),
+        Without<TInactive>,
+    >,
+    enemy_query: Query<(
+        Entity,
+        &CTransform,
+        &CCollision,
+        &CShape,
+        &CScore,
+        Option<&CTelegraph>,
+        &TEnemy,
+    )>,
+    player_query: Query<(Entity, &CTransform, &CCollision, &CShape, &CSlot, &TPlayer)>,
+    hostile_query: Query<
+        (Entity, &CTransform, &CCollision, &CShape, Has<CHealth>),
+        (With<THostile>, Without<CTelegraph>, Without<TInactive>),
+    >,
     player_config: Res<PlayerConfig>,
     enemy_config: Res<EnemyConfig>,
-    window: Query<&Window>,
+    score_config: Res<ScoreConfig>,
+    arena: Res<Arena>,
+    mut events: EventWriter<GameEvent>,
+    mut collisions: ResMut<CollisionCounts>,
 ) {
-    let p = player.get_single_mut();
-    for (e_e, e_tf, e_c, e_sh, e_sc, _) in enemy_query.iter() {
+    // bullets despawned earlier this tick, their despawn command hasn't been applied yet
+    let mut spent = Vec::new();
+    let mut destroyed = Vec::new();
+    let mut dead = Vec::new();
+    for (e_e, e_tf, e_c, e_sh, e_sc, telegraph, _) in enemy_query.iter() {
+        if telegraph.is_some() {
+            continue;
+        }
         // enemy-player collisions
-        if let Ok((p_e, p_tf, p_c, _)) = p {
-            if is_collision(&e_tf.pos, &e_c.rad, &p_tf.pos, &p_c.rad) {
-                commands.entity(p_e).despawn();
+        let hit = player_query.iter().find(|(p_e, p_tf, p_c, p_sh, _, _)| {
+            !dead.contains(p_e) && collisions.record(is_collision(e_tf, e_c, e_sh, p_tf, p_c, p_sh))
+        });
+        if let Some((p_e, _, _, _, slot, _)) = hit {
+            commands.entity(p_e).despawn();
+            commands.entity(e_e).despawn();
+            spawn_small_enemies(
+                &mut commands,
+                &mut pools.fragments,
+                e_tf,
+                e_sh,
+                &enemy_config,
+                COwner(slot.0),
+            );
+            lose_life(&mut players, &mut combo, &mut events, slot.0);
+            dead.push(p_e);
+            destroyed.push(e_e);
+            continue;
+        }
+        // enemy-bullet collisions
+        for (b_e, b_tf, b_c, b_sh, owner, pierce, _) in bullet_query.iter_mut() {
+            if spent.contains(&b_e) {
+                continue;
+            }
+            if collisions.record(is_collision(e_tf, e_c, e_sh, b_tf, b_c, b_sh)) {
                 commands.entity(e_e).despawn();
-                spawn_small_enemies(&mut commands, e_tf, e_sh, &enemy_config.small_lifespan);
-                let window = window.single();
-                spawn_player(
+                match pierce {
+                    Some(mut pierce) if pierce.0 > 0 => pierce.0 -= 1,
+                    _ => {
+                        pools.release(&mut commands, b_e);
+                        spent.push(b_e);
+                    }
+                }
+                let points = (e_sc.0 as f32 * combo.multiplier).round() as u32;
+                if let Some(state) = players.0.get_mut(owner.0) {
+                    state.score += points;
+                }
+                combo.multiplier =
+                    (combo.multiplier + score_config.combo_step).min(score_config.max_multiplier);
+                combo.since_kill = 0.;
+                events.send(GameEvent::Hit {
+                    slot: owner.0,
+                    bullet: b_e,
+                });
+                events.send(GameEvent::Kill {
+                    slot: owner.0,
+                    vertices: e_sh.vertices,
+                });
+                spawn_popup(&mut commands, e_tf.pos, points, &score_config);
+                spawn_small_enemies(
                     &mut commands,
-                    &player_config,
-                    &mut total_score,
-                    &window.width(),
-                    &window.height(),
+                    &mut pools.fragments,
+                    e_tf,
+                    e_sh,
+                    &enemy_config,
+                    *owner,
                 );
+                destroyed.push(e_e);
                 break;
             }
         }
-        // enemy-bullet collisions
-        for (b_e, b_tf, b_c, _) in bullet_query.iter() {
-            if is_collision(&e_tf.pos, &e_c.rad, &b_tf.pos, &b_c.rad) {
-                commands.entity(e_e).despawn();
-                commands.entity(b_e).despawn();
-                total_score.0 += e_sc.0;
-                spawn_small_enemies(&mut commands, e_tf, e_sh, &enemy_config.small_lifespan);
-                break;
+    }
+    // projectiles are used up by the hit, bosses carry on
+    for (h_e, h_tf, h_c, h_sh, boss) in hostile_query.iter() {
+        let hit = player_query.iter().find(|(p_e, p_tf, p_c, p_sh, _, _)| {
+            !dead.contains(p_e) && collisions.record(is_collision(h_tf, h_c, h_sh, p_tf, p_c, p_sh))
+        });
+        if let Some((p_e, _, _, _, slot, _)) = hit {
+            commands.entity(p_e).despawn();
+            if !boss {
+                pools.release(&mut commands, h_e);
             }
+            lose_life(&mut players, &mut combo, &mut events, slot.0);
+            dead.push(p_e);
+        }
+    }
+    if dead.is_empty() {
+        return;
+    }
+
+    // players respawn one by one at the safest point left, so they don't land on each other
+    let mut threats: Vec<Vec2> = enemy_query
+        .iter()
+        .filter(|other| !destroyed.contains(&other.0))
+        .map(|other| other.1.pos)
+        .collect();
+    let game_over = players.0.iter().all(|state| state.lives == 0);
+    if game_over {
+        info!("Game over, scores: {:?}", players.0);
+        commands.insert_resource(BossSchedule::default());
+        for state in players.0.iter_mut() {
+            *state = PlayerState {
+                score: 0,
+                lives: player_config.lives.max(1),
+            };
         }
     }
+    threats.extend(
+        player_query
+            .iter()
+            .filter(|p| !dead.contains(&p.0))
+            .map(|p| p.1.pos),
+    );
+    for slot in 0..players.0.len() {
+        let respawns = if game_over {
+            true
+        } else {
+            players.0[slot].lives > 0
+                && player_query
+                    .iter()
+                    .any(|(p_e, _, _, _, p_slot, _)| p_slot.0 == slot && dead.contains(&p_e))
+        };
+        if respawns {
+            let pos = safest_point(&arena, player_config.shape_radius, &threats);
+            spawn_player(&mut commands, &player_config, slot, pos);
+            threats.push(pos);
+        }
+    }
+}
+
+/// Lets the multiplier fall back towards 1 once no kill has happened for a while.
+fn s_combo(
+    mut combo: ResMut<Combo>,
+    score_config: Res<ScoreConfig>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
+) {
+    combo.since_kill += scale.delta(&time);
+    if combo.since_kill > score_config.combo_window {
+        combo.multiplier =
+            (combo.multiplier - score_config.combo_decay * scale.delta(&time)).max(1.);
+    }
 }
 
+fn s_popups(
+    mut query: Query<&mut CTransform, With<TPopup>>,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
+) {
+    for mut tf in query.iter_mut() {
+        tf.prev_pos = tf.pos;
+        let vel = tf.vel;
+        tf.pos += vel * scale.delta(&time);
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn s_lifespan(
-    mut total_score: ResMut<TotalScore>,
+    mut players: ResMut<Players>,
     mut commands: Commands,
-    mut query: Query<(Entity, &mut CLifespan, Option<&CScore>)>,
-    time: Res<Time>,
+    mut pools: ResMut<Pools>,
+    mut query: Query<
+        (Entity, &mut CLifespan, Option<&CScore>, Option<&COwner>),
+        Without<TInactive>,
+    >,
+    time: Res<Time<Fixed>>,
+    scale: Res<TimeScale>,
 ) {
-    for (e, mut ls, maybe_sc) in query.iter_mut() {
-        ls.remaining -= time.delta_seconds();
+    for (e, mut ls, maybe_sc, maybe_owner) in query.iter_mut() {
+        ls.remaining -= scale.delta(&time);
         if ls.remaining < 0. {
-            commands.entity(e).despawn();
-            if let Some(score) = maybe_sc {
-                total_score.0 += score.0;
+            pools.release(&mut commands, e);
+            if let (Some(score), Some(owner)) = (maybe_sc, maybe_owner) {
+                if let Some(state) = players.0.get_mut(owner.0) {
+                    state.score += score.0;
+                }
             }
         }
     }
@@ -465,94 +1364,308 @@ fn s_lifespan(
 
 // region: functions
 
-fn spawn_small_enemies(commands: &mut Commands, tf: &CTransform, s: &CShape, small_lifespan: &f32) {
+fn spawn_small_enemies(
+    commands: &mut Commands,
+    pool: &mut EntityPool,
+    tf: &CTransform,
+    s: &CShape,
+    enemy_config: &EnemyConfig,
+    owner: COwner,
+) {
     for i in 0..(s.vertices) {
         let i = i as f32;
-        let vel = Vec2::from_angle(tf.angle + (i * 2. * PI / s.vertices as f32));
-        commands.spawn((
-            CTransform {
-                pos: tf.pos,
-                vel,
-                angle: tf.angle * i,
-            },
-            CShape {
-                radius: s.radius / 2.,
-                color: s.color,
-                vertices: s.vertices,
-            },
-            CLifespan {
-                remaining: *small_lifespan,
-                total: *small_lifespan,
-            },
-            CScore(s.vertices * 100),
-        ));
+        let vel = Vec2::from_angle(tf.angle + (i * 2. * PI / s.vertices as f32))
+            * enemy_config.small_speed;
+        pool.spawn(
+            commands,
+            (
+                CTransform::new(tf.pos, vel, tf.angle * i),
+                CShape {
+                    radius: s.radius / 2.,
+                    color: s.color,
+                    outline: s.outline,
+                    thickness: s.thickness,
+                    vertices: s.vertices,
+                },
+                CLifespan {
+                    remaining: enemy_config.small_lifespan,
+                    total: enemy_config.small_lifespan,
+                },
+                CScore(s.vertices * 100),
+                CBoundary(enemy_config.small_boundary),
+                owner,
+                TFragment,
+            ),
+        );
     }
 }
 
-fn is_collision(pos1: &Vec2, rad1: &f32, pos2: &Vec2, rad2: &f32) -> bool {
-    let diff = *pos1 - *pos2;
-    let dist_sq = diff.x * diff.x + diff.y * diff.y;
-    dist_sq < (rad1 + rad2) * (rad1 + rad2)
+/// Moves and spins the entity for one tick, returning whether it met the window edge: bounced
+/// off it, wrapped around, was stopped by it or is now fully outside the window.
+fn transform_tick(
+    tf: &mut CTransform,
+    radius: f32,
+    boundary: BoundaryMode,
+    width: f32,
+    height: f32,
+    delta_seconds: f32,
+) -> bool {
+    tf.prev_pos = tf.pos;
+    tf.pos += tf.vel * delta_seconds;
+
+    // wall collisions
+    let at_edge = match boundary {
+        BoundaryMode::Bounce => {
+            let mut bounced = false;
+            if tf.pos.x + radius > width || tf.pos.x - radius < 0. {
+                tf.vel.x = -tf.vel.x;
+                tf.pos.x += tf.vel.x * 2. * delta_seconds;
+                bounced = true;
+            }
+            if tf.pos.y + radius > height || tf.pos.y - radius < 0. {
+                tf.vel.y = -tf.vel.y;
+                tf.pos.y += tf.vel.y * 2. * delta_seconds;
+                bounced = true;
+            }
+            bounced
+        }
+        BoundaryMode::Wrap => {
+            let pos = tf.pos;
+            tf.pos.x = wrap(tf.pos.x, radius, width);
+            tf.pos.y = wrap(tf.pos.y, radius, height);
+            let wrapped = tf.pos != pos;
+            if wrapped {
+                // don't interpolate across the whole window
+                tf.prev_pos = tf.pos;
+            }
+            wrapped
+        }
+        BoundaryMode::Despawn => {
+            tf.pos.x + radius < 0.
+                || tf.pos.x - radius > width
+                || tf.pos.y + radius < 0.
+                || tf.pos.y - radius > height
+        }
+        BoundaryMode::Clamp => {
+            let pos = tf.pos;
+            tf.pos = tf
+                .pos
+                .max(Vec2::splat(radius))
+                .min(Vec2::new(width - radius, height - radius));
+            tf.pos != pos
+        }
+    };
+
+    // rotation
+    tf.angle += delta_seconds;
+    at_edge
+}
+
+/// Moves a coordinate that has fully left `0..size` to just outside the opposite side.
+fn wrap(x: f32, radius: f32, size: f32) -> f32 {
+    if x + radius < 0. {
+        x + size + 2. * radius
+    } else if x - radius > size {
+        x - size - 2. * radius
+    } else {
+        x
+    }
 }
 
-fn transform_tick(tf: &mut CTransform, radius: f32, width: f32, height: f32, delta_seconds: f32) {
-    tf.pos = tf.pos + tf.vel;
+/// Takes a life from the player in `slot`, who was just hit, and ends the combo.
+fn lose_life(
+    players: &mut Players,
+    combo: &mut Combo,
+    events: &mut EventWriter<GameEvent>,
+    slot: usize,
+) {
+    *combo = Combo::default();
+    if let Some(state) = players.0.get_mut(slot) {
+        state.lives = state.lives.saturating_sub(1);
+    }
+    events.send(GameEvent::Death { slot });
+}
 
-    // wall collisions
-    if tf.pos.x + radius > width || tf.pos.x - radius < 0. {
-        tf.vel.x = -tf.vel.x;
-        tf.pos.x = tf.pos.x + tf.vel.x * 2.;
+/// Fires a projectile that hurts players, from a boss or an enemy.
+fn spawn_projectile(commands: &mut Commands, pos: Vec2, vel: Vec2, projectile: &ProjectileType) {
+    commands.spawn((
+        CTransform::new(pos, vel, 0.),
+        CShape {
+            radius: projectile.shape_radius,
+            color: rgb(projectile.color),
+            outline: rgb(projectile.outline_color),
+            thickness: projectile.outline_thickness,
+            vertices: projectile.vertices,
+        },
+        CCollision {
+            rad: projectile.collision_radius,
+            mode: CollisionMode::Circle,
+        },
+        CLifespan {
+            remaining: projectile.lifespan,
+            total: projectile.lifespan,
+        },
+        CBoundary(BoundaryMode::Despawn),
+        THostile,
+    ));
+}
+
+fn spawn_popup(commands: &mut Commands, pos: Vec2, points: u32, score_config: &ScoreConfig) {
+    commands.spawn((
+        CTransform::new(pos, Vec2::new(0., score_config.popup_speed), 0.),
+        CLifespan {
+            remaining: score_config.popup_lifespan,
+            total: score_config.popup_lifespan,
+        },
+        TPopup(points),
+    ));
+}
+
+/// Fires one shot of `bullet` from `pos` in the `aim` direction, fanning multiple bullets out
+/// evenly over the spread angle. Returns the bullets fired.
+fn spawn_bullets(
+    commands: &mut Commands,
+    pool: &mut EntityPool,
+    pos: Vec2,
+    aim: Vec2,
+    bullet: &BulletType,
+    boundary: BoundaryMode,
+    owner: CSlot,
+) -> Vec<Entity> {
+    let aim = aim.normalize_or_zero();
+    if aim == Vec2::ZERO {
+        return Vec::new();
     }
-    if tf.pos.y + radius > height || tf.pos.y - radius < 0. {
-        tf.vel.y = -tf.vel.y;
-        tf.pos.y = tf.pos.y + tf.vel.y * 2.;
+    let mut bullets = Vec::with_capacity(bullet.count as usize);
+    let spread = bullet.spread.to_radians();
+    for i in 0..bullet.count {
+        let offset = if bullet.count > 1 {
+            -spread / 2. + spread * i as f32 / (bullet.count - 1) as f32
+        } else {
+            0.
+        };
+        let mut e = pool.spawn(
+            commands,
+            (
+                CShape {
+                    radius: bullet.shape_radius,
+                    color: rgb(bullet.color),
+                    outline: rgb(bullet.outline_color),
+                    thickness: bullet.outline_thickness,
+                    vertices: bullet.vertices,
+                },
+                CTransform::new(pos, Vec2::from_angle(offset).rotate(aim) * bullet.speed, 0.),
+                CLifespan {
+                    remaining: bullet.lifespan,
+                    total: bullet.lifespan,
+                },
+                CCollision {
+                    rad: bullet.collision_radius,
+                    mode: bullet.collision_mode,
+                },
+                CBoundary(boundary),
+                COwner(owner.0),
+                TBullet,
+            ),
+        );
+        // a reused bullet may still carry the extras of another type
+        e.remove::<(CPierce, CHoming, CBounces, CStruck)>();
+        if bullet.pierce > 0 {
+            e.insert(CPierce(bullet.pierce));
+        }
+        if bullet.homing > 0. {
+            e.insert(CHoming(bullet.homing));
+        }
+        if let Some(bounces) = bullet.bounces {
+            e.insert(CBounces(bounces));
+        }
+        bullets.push(e.id());
     }
+    bullets
+}
 
-    // rotation
-    tf.angle += delta_seconds;
+/// The point of a grid over the arena farthest from every threat, preferring the center.
+fn safest_point(arena: &Arena, radius: f32, threats: &[Vec2]) -> Vec2 {
+    let center = Vec2::new(arena.width / 2., arena.height / 2.);
+    let min = Vec2::splat(radius);
+    let max = Vec2::new(arena.width - radius, arena.height - radius).max(min);
+    let clearance = |p: Vec2| {
+        threats
+            .iter()
+            .map(|t| t.distance_squared(p))
+            .fold(f32::MAX, f32::min)
+    };
+    let mut best = center;
+    let mut best_clearance = clearance(center);
+    for i in 0..=SAFE_GRID.0 {
+        for j in 0..=SAFE_GRID.1 {
+            let t = Vec2::new(i as f32 / SAFE_GRID.0 as f32, j as f32 / SAFE_GRID.1 as f32);
+            let p = min + (max - min) * t;
+            let c = clearance(p);
+            if c > best_clearance {
+                best = p;
+                best_clearance = c;
+            }
+        }
+    }
+    best
 }
 
-fn spawn_player(
+/// Starts the game over for every player slot: full lives, no score and everyone spread evenly
+/// across the middle of the arena.
+fn spawn_players(
     commands: &mut Commands,
-    player_config: &Res<PlayerConfig>,
-    total_score: &mut TotalScore,
-    width: &f32,
-    height: &f32,
+    player_config: &PlayerConfig,
+    arena: &Arena,
+    players: &mut Players,
 ) {
-    total_score.0 = 0;
+    let count = player_config.players();
+    players.0 = vec![
+        PlayerState {
+            score: 0,
+            lives: player_config.lives.max(1),
+        };
+        count
+    ];
+    for slot in 0..count {
+        let pos = Vec2::new(
+            arena.width * (slot + 1) as f32 / (count + 1) as f32,
+            arena.height / 2.,
+        );
+        spawn_player(commands, player_config, slot, pos);
+    }
+}
+
+fn spawn_player(commands: &mut Commands, player_config: &PlayerConfig, slot: usize, pos: Vec2) {
+    let color = player_config.slots[slot].color;
     commands.spawn((
-        CTransform {
-            pos: Vec2 {
-                x: width / 2.,
-                y: height / 2.,
-            },
-            vel: Vec2 { x: 0., y: 0. },
-            angle: 0.,
-        },
+        CTransform::new(pos, Vec2 { x: 0., y: 0. }, 0.),
         CShape {
             radius: player_config.shape_radius,
-            color: Color::rgba(
-                player_config.color.0,
-                player_config.color.1,
-                player_config.color.2,
-                1.,
-            ),
+            color: rgb(color),
+            outline: rgb(player_config.outline_color),
+            thickness: player_config.outline_thickness,
             vertices: player_config.vertices,
         },
         CCollision {
             rad: player_config.collision_radius,
+            mode: player_config.collision_mode,
+        },
+        CBoundary(player_config.boundary),
+        CInput {
+            facing: Vec2::Y,
+            ..Default::default()
         },
-        CInput::default(),
+        CWeapon::default(),
+        CCooldown::default(),
+        CSlot(slot),
         TPlayer,
     ));
 }
 
-fn rng_range(rng: &mut GlobalEntropy<WyRand>, min: f32, max: f32) -> f32 {
-    min + (rng.next_u32() as f32) / ((u32::MAX as f32) / (max - min))
-}
-
-fn rng_range_u32(rng: &mut GlobalEntropy<WyRand>, min: u32, max: u32) -> u32 {
-    min + (rng.next_u32() % (1 + max - min))
+/// An opaque color from a config's `(r, g, b)`.
+fn rgb(c: (f32, f32, f32)) -> Color {
+    Color::rgba(c.0, c.1, c.2, 1.)
 }
 
 // endregion
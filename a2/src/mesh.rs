@@ -0,0 +1,299 @@
+//! The `Renderer::Mesh` way of drawing shapes: every [`CShape`] gets a filled polygon mesh and a
+//! separate outline entity whose ring mesh sits just outside it. Meshes are shared between shapes
+//! of the same size, materials are per entity so each can fade on its own.
+
+// region: includes
+
+use std::f32::consts::{PI, TAU};
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    utils::HashMap,
+};
+
+use crate::config::{Renderer, WindowConfig};
+use crate::debug::DebugOverlay;
+use crate::pool::TInactive;
+use crate::{AppState, CLifespan, CShape, CTelegraph, CTransform};
+
+// endregion
+
+// region: Mesh Structs
+
+/// Radius, vertex count and outline thickness, the floats as bits so they can be hashed.
+type MeshKey = (u32, u32, u32);
+
+/// The polygon meshes built so far, fills by radius and vertex count only.
+#[derive(Resource, Default)]
+struct ShapeMeshes {
+    fills: HashMap<(u32, u32), Handle<Mesh>>,
+    outlines: HashMap<MeshKey, Handle<Mesh>>,
+}
+
+/// The outline entity drawn around a shape and the shape its meshes were built for.
+#[derive(Component)]
+struct CShapeMesh {
+    key: MeshKey,
+    outline: Entity,
+}
+
+/// The outline of the shape on this entity. It follows the entity and despawns with it.
+#[derive(Component)]
+struct TOutline(Entity);
+
+// endregion
+
+pub struct ShapeMeshPlugin;
+
+impl Plugin for ShapeMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShapeMeshes>().add_systems(
+            Update,
+            (s_attach_meshes, apply_deferred, s_sync_meshes)
+                .chain()
+                .run_if(in_state(AppState::InGame))
+                .run_if(|window_config: Res<WindowConfig>| {
+                    window_config.renderer == Renderer::Mesh
+                }),
+        );
+    }
+}
+
+// region: systems
+
+fn s_attach_meshes(
+    mut commands: Commands,
+    mut shape_meshes: ResMut<ShapeMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &CShape), Without<CShapeMesh>>,
+) {
+    for (e, shape) in query.iter() {
+        let key = mesh_key(shape);
+        let (fill, outline) = shape_meshes.get(&mut meshes, key);
+        let outline = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: outline.into(),
+                    material: materials.add(ColorMaterial::from(shape.outline)),
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                },
+                TOutline(e),
+            ))
+            .id();
+        commands.entity(e).insert((
+            MaterialMesh2dBundle {
+                mesh: fill.into(),
+                material: materials.add(ColorMaterial::from(shape.color)),
+                visibility: Visibility::Hidden,
+                ..Default::default()
+            },
+            CShapeMesh { key, outline },
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn s_sync_meshes(
+    mut commands: Commands,
+    mut shape_meshes: ResMut<ShapeMeshes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    overlay: Res<DebugOverlay>,
+    fixed_time: Res<Time<Fixed>>,
+    mut shapes: Query<
+        (
+            &CShape,
+            &CTransform,
+            &mut CShapeMesh,
+            &mut Mesh2dHandle,
+            &Handle<ColorMaterial>,
+            &mut Transform,
+            &mut Visibility,
+            (Option<&CLifespan>, Option<&CTelegraph>, Has<TInactive>),
+        ),
+        Without<TOutline>,
+    >,
+    mut outlines: Query<
+        (
+            Entity,
+            &TOutline,
+            &mut Mesh2dHandle,
+            &Handle<ColorMaterial>,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        Without<CShapeMesh>,
+    >,
+) {
+    // how far we are between the last fixed tick and the next one
+    let alpha = fixed_time.overstep_percentage();
+
+    for (
+        shape,
+        tf,
+        mut shape_mesh,
+        mut fill_mesh,
+        fill_material,
+        mut transform,
+        mut visibility,
+        (lifespan, telegraph, inactive),
+    ) in shapes.iter_mut()
+    {
+        let Ok((
+            _,
+            _,
+            mut outline_mesh,
+            outline_material,
+            mut outline_transform,
+            mut outline_visibility,
+        )) = outlines.get_mut(shape_mesh.outline)
+        else {
+            continue;
+        };
+
+        // pooled entities come back as other shapes
+        let key = mesh_key(shape);
+        if key != shape_mesh.key {
+            let (fill, outline) = shape_meshes.get(&mut meshes, key);
+            *fill_mesh = fill.into();
+            *outline_mesh = outline.into();
+            shape_mesh.key = key;
+        }
+
+        *transform = Transform::from_translation(tf.prev_pos.lerp(tf.pos, alpha).extend(0.))
+            .with_rotation(Quat::from_rotation_z(-tf.angle));
+        *outline_transform = *transform;
+
+        *visibility = if overlay.shapes && !inactive {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        *outline_visibility = *visibility;
+
+        // telegraphed enemies are only an outline fading in, fragments and bullets fade out
+        let (fill_alpha, outline_alpha) = match (lifespan, telegraph) {
+            (Some(ls), _) => (ls.remaining / ls.total, ls.remaining / ls.total),
+            (_, Some(t)) => (0., 1. - t.remaining / t.total),
+            _ => (shape.color.a(), shape.color.a()),
+        };
+        set_color(
+            &mut materials,
+            fill_material,
+            shape.color.with_a(fill_alpha),
+        );
+        set_color(
+            &mut materials,
+            outline_material,
+            shape.outline.with_a(outline_alpha),
+        );
+    }
+
+    // outlines of shapes that were despawned
+    for (e, owner, ..) in outlines.iter() {
+        if !shapes.contains(owner.0) {
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+// endregion
+
+// region: functions
+
+impl ShapeMeshes {
+    fn get(&mut self, meshes: &mut Assets<Mesh>, key: MeshKey) -> (Handle<Mesh>, Handle<Mesh>) {
+        let (radius, vertices, thickness) = (f32::from_bits(key.0), key.1, f32::from_bits(key.2));
+        let fill = self
+            .fills
+            .entry((key.0, key.1))
+            .or_insert_with(|| meshes.add(polygon_mesh(radius, vertices)))
+            .clone();
+        let outline = self
+            .outlines
+            .entry(key)
+            .or_insert_with(|| meshes.add(outline_mesh(radius, vertices, thickness)))
+            .clone();
+        (fill, outline)
+    }
+}
+
+fn mesh_key(shape: &CShape) -> MeshKey {
+    (
+        shape.radius.to_bits(),
+        shape.vertices,
+        shape.thickness.to_bits(),
+    )
+}
+
+/// Only touches the material when the color changed, so unchanged ones aren't uploaded again.
+fn set_color(materials: &mut Assets<ColorMaterial>, handle: &Handle<ColorMaterial>, color: Color) {
+    if materials.get(handle).is_some_and(|m| m.color == color) {
+        return;
+    }
+    if let Some(material) = materials.get_mut(handle) {
+        material.color = color;
+    }
+}
+
+/// Corners of a regular polygon around the origin, in the order `collision::polygon` and
+/// `Gizmos::arc_2d` put them at an angle of 0.
+pub fn polygon_corners(radius: f32, vertices: u32) -> Vec<Vec2> {
+    (0..vertices)
+        .map(|i| Vec2::from((-PI + i as f32 * TAU / vertices as f32).sin_cos()) * radius)
+        .collect()
+}
+
+/// A triangle fan from the center to every corner.
+pub fn polygon_mesh(radius: f32, vertices: u32) -> Mesh {
+    let corners = polygon_corners(radius, vertices);
+    let positions: Vec<Vec2> = std::iter::once(Vec2::ZERO).chain(corners).collect();
+    // the corners go round clockwise, so every triangle is turned to face the camera
+    let indices = (1..=vertices)
+        .flat_map(|i| [0, i % vertices + 1, i])
+        .collect();
+    build_mesh(&positions, radius, indices)
+}
+
+/// A band `thickness` wide around the outside of the polygon. The outer corners are pushed out
+/// further than the thickness so every edge of the band is equally wide.
+pub fn outline_mesh(radius: f32, vertices: u32, thickness: f32) -> Mesh {
+    let outer = radius + thickness / (PI / vertices as f32).cos();
+    let positions: Vec<Vec2> = polygon_corners(radius, vertices)
+        .into_iter()
+        .chain(polygon_corners(outer, vertices))
+        .collect();
+    // two triangles per edge, inner corners first then outer, counter-clockwise like the fill
+    let indices = (0..vertices)
+        .flat_map(|i| {
+            let next = (i + 1) % vertices;
+            [i, next, vertices + i, next, vertices + next, vertices + i]
+        })
+        .collect();
+    build_mesh(&positions, outer, indices)
+}
+
+fn build_mesh(positions: &[Vec2], extent: f32, indices: Vec<u32>) -> Mesh {
+    let uvs: Vec<[f32; 2]> = positions
+        .iter()
+        .map(|p| [0.5 + p.x / (2. * extent), 0.5 - p.y / (2. * extent)])
+        .collect();
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        positions
+            .iter()
+            .map(|p| [p.x, p.y, 0.])
+            .collect::<Vec<[f32; 3]>>(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; positions.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+// endregion
@@ -0,0 +1,771 @@
+//! Client/server play over UDP. The server runs the simulation as usual and sends a snapshot
+//! of every shape after each tick, clients only send their input and draw the snapshots a
+//! little behind the newest one, interpolating between them.
+
+// region: includes
+
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_rand::prelude::WyRand;
+use comp4300_common::rng::RandomExt;
+use rand_core::SeedableRng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::config::*;
+use crate::pool::TInactive;
+use crate::replay::ReplayFrame;
+use crate::{
+    s_shoot, s_telegraph, AppState, CInput, CLifespan, CShape, CSlot, CTelegraph, CTransform,
+    Combo, Focus, PlayerState, Players,
+};
+
+// endregion
+
+// inputs resent with every packet, so a lost one doesn't drop a shot
+const INPUT_REDUNDANCY: usize = 4;
+// entities per snapshot packet, keeps each datagram well under the UDP size limit
+const SNAPSHOT_CHUNK: usize = 128;
+// complete snapshots kept for interpolation
+const SNAPSHOT_BUFFER: usize = 60;
+// seconds of silence before the server frees a client's slot
+const CLIENT_TIMEOUT: f64 = 5.;
+// seconds between connection attempts while the server hasn't answered
+const HELLO_INTERVAL: f64 = 1.;
+// ticks the client clock may drift from its target before it jumps instead of easing
+const MAX_CLOCK_DRIFT: f64 = 30.;
+
+// region: Net Structs
+
+#[derive(Serialize, Deserialize, Debug)]
+enum ClientMessage {
+    Hello,
+    /// the newest inputs, oldest first, the last one for tick `seq`
+    Input {
+        seq: u32,
+        frames: Vec<ReplayFrame>,
+    },
+    Bye,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum ServerMessage {
+    Welcome { slot: usize },
+    Full,
+    Snapshot(Snapshot),
+}
+
+/// One part of the world after a server tick.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Snapshot {
+    tick: u32,
+    part: u16,
+    parts: u16,
+    /// score and lives by slot
+    players: Vec<(u32, u32)>,
+    multiplier: f32,
+    /// the shared focus meter
+    focus: f32,
+    entities: Vec<NetEntity>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+struct NetEntity {
+    id: u64,
+    slot: Option<usize>,
+    pos: (f32, f32),
+    angle: f32,
+    radius: f32,
+    color: (f32, f32, f32, f32),
+    outline: (f32, f32, f32),
+    thickness: f32,
+    vertices: u32,
+}
+
+/// A non-blocking UDP socket that can hold back or drop what it sends, to test the netcode
+/// against a bad network on localhost.
+pub struct Link {
+    socket: UdpSocket,
+    lag: f32,
+    jitter: f32,
+    loss: f32,
+    rng: WyRand,
+    // packets waiting out their simulated delay, with the time they go out
+    delayed: Vec<(f64, SocketAddr, Vec<u8>)>,
+}
+
+struct RemoteClient {
+    slot: usize,
+    last_seq: Option<u32>,
+    last_heard: f64,
+    input: ReplayFrame,
+}
+
+#[derive(Resource)]
+pub struct NetServer {
+    link: Link,
+    clients: HashMap<SocketAddr, RemoteClient>,
+    tick: u32,
+}
+
+#[derive(Resource)]
+pub struct NetClient {
+    link: Link,
+    server: SocketAddr,
+    slot: Option<usize>,
+    last_hello: f64,
+    seq: u32,
+    sent: VecDeque<ReplayFrame>,
+    // parts of the snapshot being received, for the newest tick seen
+    partial: Vec<Snapshot>,
+    snapshots: VecDeque<Snapshot>,
+    // server tick being shown, fractional between snapshots
+    clock: Option<f64>,
+    ghosts: HashMap<u64, Entity>,
+}
+
+/// The input of the player on this side of a client, aimed from where the server has them.
+#[derive(Component)]
+struct TNetInput;
+
+/// A shape mirrored from the server.
+#[derive(Component)]
+struct TNetGhost;
+
+// endregion
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            (
+                s_server_start.run_if(net_mode(NetMode::Server)),
+                s_client_start.run_if(net_mode(NetMode::Client)),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                (
+                    s_server_receive.before(s_shoot),
+                    s_server_send.after(s_telegraph),
+                )
+                    .run_if(resource_exists::<NetServer>()),
+                s_client_send.run_if(resource_exists::<NetClient>()),
+            )
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            Update,
+            (s_client_receive, s_client_interpolate)
+                .chain()
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<NetClient>()),
+        )
+        .add_systems(
+            Last,
+            (
+                s_link_flush,
+                s_client_bye.run_if(resource_exists::<NetClient>()),
+            ),
+        );
+    }
+}
+
+fn net_mode(mode: NetMode) -> impl FnMut(Option<Res<NetConfig>>) -> bool + Clone {
+    move |config: Option<Res<NetConfig>>| config.is_some_and(|c| c.mode == mode)
+}
+
+/// Whether this side runs the game itself, false on clients that only show the server's.
+pub fn simulates(config: Option<Res<NetConfig>>) -> bool {
+    !config.is_some_and(|c| c.mode == NetMode::Client)
+}
+
+// region: systems
+
+fn s_server_start(mut commands: Commands, net_config: Res<NetConfig>) {
+    match Link::bind(&net_config.address, &net_config) {
+        Ok(link) => {
+            let server = NetServer {
+                link,
+                clients: HashMap::default(),
+                tick: 0,
+            };
+            // the real port, when the config asks for any free one
+            if let Some(address) = server.local_addr() {
+                info!("Server listening on {}", address);
+            }
+            commands.insert_resource(server);
+        }
+        Err(err) => error!("net.rs::s_server_start: {}", err),
+    }
+}
+
+fn s_server_receive(
+    mut server: ResMut<NetServer>,
+    players: Res<Players>,
+    mut inputs: Query<(&CSlot, &mut CInput)>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let server = server.as_mut();
+    for (from, message) in server.link.receive::<ClientMessage>() {
+        match message {
+            ClientMessage::Hello => {
+                let slot = match server.clients.get(&from) {
+                    Some(client) => Some(client.slot),
+                    None => (1..players.0.len())
+                        .find(|slot| server.clients.values().all(|c| c.slot != *slot)),
+                };
+                let Some(slot) = slot else {
+                    server.link.send(now, from, &ServerMessage::Full);
+                    continue;
+                };
+                if !server.clients.contains_key(&from) {
+                    info!("{} joined as player {}", from, slot + 1);
+                }
+                server.clients.insert(
+                    from,
+                    RemoteClient {
+                        slot,
+                        last_seq: None,
+                        last_heard: now,
+                        input: ReplayFrame::default(),
+                    },
+                );
+                server
+                    .link
+                    .send(now, from, &ServerMessage::Welcome { slot });
+            }
+            ClientMessage::Input { seq, frames } => {
+                let Some(client) = server.clients.get_mut(&from) else {
+                    continue;
+                };
+                client.last_heard = now;
+                let first = (seq + 1).saturating_sub(frames.len() as u32);
+                for (i, frame) in frames.iter().enumerate() {
+                    let frame_seq = first + i as u32;
+                    if client.last_seq.is_some_and(|last| frame_seq <= last) {
+                        continue;
+                    }
+                    // held state follows the newest frame, presses add up until they're used
+                    let input = &mut client.input;
+                    input.up = frame.up;
+                    input.left = frame.left;
+                    input.right = frame.right;
+                    input.down = frame.down;
+                    input.held = frame.held;
+                    input.shoot = frame.shoot.or(input.shoot);
+                    input.cycle += frame.cycle;
+                    input.focus = frame.focus;
+                    client.last_seq = Some(frame_seq);
+                }
+            }
+            ClientMessage::Bye => {
+                if let Some(client) = server.clients.remove(&from) {
+                    info!("{} left, player {} is free", from, client.slot + 1);
+                }
+            }
+        }
+    }
+    server.clients.retain(|from, client| {
+        let alive = now - client.last_heard < CLIENT_TIMEOUT;
+        if !alive {
+            info!("{} timed out, player {} is free", from, client.slot + 1);
+        }
+        alive
+    });
+
+    for (slot, mut input) in inputs.iter_mut() {
+        let Some(client) = server.clients.values_mut().find(|c| c.slot == slot.0) else {
+            continue;
+        };
+        let remote = &mut client.input;
+        input.up = remote.up;
+        input.left = remote.left;
+        input.right = remote.right;
+        input.down = remote.down;
+        input.held = remote.held.map(|(x, y)| Vec2::new(x, y));
+        input.shoot = remote.shoot.take().map(|(x, y)| Vec2::new(x, y));
+        input.cycle = std::mem::take(&mut remote.cycle);
+        input.focus = remote.focus;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn s_server_send(
+    mut server: ResMut<NetServer>,
+    players: Res<Players>,
+    combo: Res<Combo>,
+    focus: Res<Focus>,
+    query: Query<
+        (
+            Entity,
+            &CTransform,
+            &CShape,
+            Option<&CSlot>,
+            Option<&CLifespan>,
+            Option<&CTelegraph>,
+        ),
+        Without<TInactive>,
+    >,
+    time: Res<Time<Real>>,
+) {
+    server.tick += 1;
+    if server.clients.is_empty() {
+        return;
+    }
+    let entities: Vec<NetEntity> = query
+        .iter()
+        .map(|(e, tf, shape, slot, lifespan, telegraph)| {
+            // the fades the server draws, so clients don't need the timers
+            let alpha = match (lifespan, telegraph) {
+                (Some(ls), _) => ls.remaining / ls.total,
+                (_, Some(t)) => 1. - t.remaining / t.total,
+                _ => shape.color.a(),
+            };
+            NetEntity {
+                id: e.to_bits(),
+                slot: slot.map(|s| s.0),
+                pos: (tf.pos.x, tf.pos.y),
+                angle: tf.angle,
+                radius: shape.radius,
+                color: (shape.color.r(), shape.color.g(), shape.color.b(), alpha),
+                outline: (shape.outline.r(), shape.outline.g(), shape.outline.b()),
+                thickness: shape.thickness,
+                vertices: shape.vertices,
+            }
+        })
+        .collect();
+    let chunks: Vec<&[NetEntity]> = if entities.is_empty() {
+        vec![&[]]
+    } else {
+        entities.chunks(SNAPSHOT_CHUNK).collect()
+    };
+
+    let now = time.elapsed_seconds_f64();
+    let server = server.as_mut();
+    let addresses: Vec<SocketAddr> = server.clients.keys().copied().collect();
+    for (part, chunk) in chunks.iter().enumerate() {
+        let message = ServerMessage::Snapshot(Snapshot {
+            tick: server.tick,
+            part: part as u16,
+            parts: chunks.len() as u16,
+            players: players.0.iter().map(|p| (p.score, p.lives)).collect(),
+            multiplier: combo.multiplier,
+            focus: focus.meter,
+            entities: chunk.to_vec(),
+        });
+        for to in addresses.iter() {
+            server.link.send(now, *to, &message);
+        }
+    }
+}
+
+fn s_client_start(mut commands: Commands, net_config: Res<NetConfig>) {
+    let server = match net_config.address.parse::<SocketAddr>() {
+        Ok(server) => server,
+        Err(err) => {
+            error!("net.rs::s_client_start: {}: {}", net_config.address, err);
+            return;
+        }
+    };
+    let any = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    match Link::bind(any, &net_config) {
+        Ok(link) => {
+            info!("Connecting to {}", server);
+            commands.insert_resource(NetClient {
+                link,
+                server,
+                slot: None,
+                last_hello: f64::MIN,
+                seq: 0,
+                sent: VecDeque::new(),
+                partial: Vec::new(),
+                snapshots: VecDeque::new(),
+                clock: None,
+                ghosts: HashMap::default(),
+            });
+        }
+        Err(err) => error!("net.rs::s_client_start: {}", err),
+    }
+}
+
+fn s_client_send(
+    mut client: ResMut<NetClient>,
+    mut input: Query<&mut CInput, With<TNetInput>>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let client = client.as_mut();
+    let server = client.server;
+    if client.slot.is_none() {
+        if now - client.last_hello >= HELLO_INTERVAL {
+            client.last_hello = now;
+            client.link.send(now, server, &ClientMessage::Hello);
+        }
+        return;
+    }
+    let Ok(mut input) = input.get_single_mut() else {
+        return;
+    };
+
+    client.seq += 1;
+    client.sent.push_back(ReplayFrame {
+        up: input.up,
+        left: input.left,
+        right: input.right,
+        down: input.down,
+        shoot: input.shoot.take().map(|aim| (aim.x, aim.y)),
+        held: input.held.map(|aim| (aim.x, aim.y)),
+        cycle: std::mem::take(&mut input.cycle),
+        focus: input.focus,
+    });
+    while client.sent.len() > INPUT_REDUNDANCY {
+        client.sent.pop_front();
+    }
+    let message = ClientMessage::Input {
+        seq: client.seq,
+        frames: client.sent.iter().copied().collect(),
+    };
+    client.link.send(now, server, &message);
+}
+
+fn s_client_receive(
+    mut commands: Commands,
+    mut client: ResMut<NetClient>,
+    mut players: ResMut<Players>,
+    mut combo: ResMut<Combo>,
+    mut focus: ResMut<Focus>,
+) {
+    let client = client.as_mut();
+    for (from, message) in client.link.receive::<ServerMessage>() {
+        if from != client.server {
+            continue;
+        }
+        match message {
+            ServerMessage::Welcome { slot } => {
+                if client.slot.is_some() {
+                    continue;
+                }
+                info!("Joined {} as player {}", client.server, slot + 1);
+                client.slot = Some(slot);
+                // read with the first slot's controls, whichever slot the server gave us
+                commands.spawn((
+                    CTransform::new(Vec2::ZERO, Vec2::ZERO, 0.),
+                    CInput {
+                        facing: Vec2::Y,
+                        ..Default::default()
+                    },
+                    CSlot(0),
+                    TNetInput,
+                ));
+            }
+            ServerMessage::Full => {
+                warn!("{} has no free player slot", client.server);
+            }
+            ServerMessage::Snapshot(part) => {
+                if let Some(tick) = client.partial.first().map(|p| p.tick) {
+                    if part.tick < tick {
+                        continue;
+                    }
+                    // a newer tick started arriving, the unfinished one is given up on
+                    if part.tick > tick {
+                        client.partial.clear();
+                    }
+                }
+                if client.partial.iter().any(|p| p.part == part.part) {
+                    continue;
+                }
+                let parts = part.parts as usize;
+                client.partial.push(part);
+                if client.partial.len() < parts {
+                    continue;
+                }
+                let mut parts = std::mem::take(&mut client.partial);
+                let mut snapshot = parts.remove(0);
+                for part in parts {
+                    snapshot.entities.extend(part.entities);
+                }
+                if client
+                    .snapshots
+                    .back()
+                    .is_some_and(|last| last.tick >= snapshot.tick)
+                {
+                    continue;
+                }
+                players.0 = snapshot
+                    .players
+                    .iter()
+                    .map(|(score, lives)| PlayerState {
+                        score: *score,
+                        lives: *lives,
+                    })
+                    .collect();
+                combo.multiplier = snapshot.multiplier;
+                focus.meter = snapshot.focus;
+                client.snapshots.push_back(snapshot);
+                while client.snapshots.len() > SNAPSHOT_BUFFER {
+                    client.snapshots.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Shows the world `interpolation_delay` behind the newest snapshot, easing the clock towards
+/// that target so lag spikes and lost packets don't make it jump.
+fn s_client_interpolate(
+    mut commands: Commands,
+    mut client: ResMut<NetClient>,
+    mut ghosts: Query<(&mut CTransform, &mut CShape), With<TNetGhost>>,
+    mut input: Query<&mut CTransform, (With<TNetInput>, Without<TNetGhost>)>,
+    net_config: Res<NetConfig>,
+    fixed_time: Res<Time<Fixed>>,
+    time: Res<Time<Real>>,
+) {
+    let client = client.as_mut();
+    let Some(newest) = client.snapshots.back().map(|s| s.tick as f64) else {
+        return;
+    };
+    let tick_rate = 1. / fixed_time.timestep().as_secs_f64();
+    let target = newest - net_config.interpolation_delay as f64 * tick_rate;
+    let clock = match client.clock {
+        Some(clock) if (target - clock).abs() < MAX_CLOCK_DRIFT => {
+            // run up to 10% fast or slow to catch up with the target
+            let rate = (1. + (target - clock) / tick_rate).clamp(0.9, 1.1);
+            clock + time.delta_seconds_f64() * tick_rate * rate
+        }
+        _ => target,
+    };
+    client.clock = Some(clock);
+
+    // the newest snapshot at or before the clock and the one after it, if any
+    let Some(from) = client
+        .snapshots
+        .iter()
+        .rposition(|s| s.tick as f64 <= clock)
+    else {
+        return;
+    };
+    let a = &client.snapshots[from];
+    let b = client.snapshots.get(from + 1).unwrap_or(a);
+    let t = if b.tick > a.tick {
+        ((clock - a.tick as f64) / (b.tick - a.tick) as f64).clamp(0., 1.) as f32
+    } else {
+        0.
+    };
+
+    let next: HashMap<u64, &NetEntity> = b.entities.iter().map(|e| (e.id, e)).collect();
+    let mut seen = HashSet::with_capacity(a.entities.len());
+    for entity in a.entities.iter() {
+        let start = Vec2::new(entity.pos.0, entity.pos.1);
+        // entities that wrapped around the window jump rather than cross it
+        let pos = match next.get(&entity.id) {
+            Some(to) => {
+                let end = Vec2::new(to.pos.0, to.pos.1);
+                if end.distance(start) < entity.radius * 4. {
+                    start.lerp(end, t)
+                } else {
+                    start
+                }
+            }
+            None => start,
+        };
+        let color = Color::rgba(
+            entity.color.0,
+            entity.color.1,
+            entity.color.2,
+            entity.color.3,
+        );
+        let outline = Color::rgb(entity.outline.0, entity.outline.1, entity.outline.2);
+        seen.insert(entity.id);
+
+        if entity.slot.is_some() && entity.slot == client.slot {
+            if let Ok(mut tf) = input.get_single_mut() {
+                tf.pos = pos;
+                tf.prev_pos = pos;
+            }
+        }
+        if let Some(Ok((mut tf, mut shape))) =
+            client.ghosts.get(&entity.id).map(|e| ghosts.get_mut(*e))
+        {
+            tf.pos = pos;
+            tf.prev_pos = pos;
+            tf.angle = entity.angle;
+            shape.radius = entity.radius;
+            shape.color = color;
+            shape.outline = outline;
+            shape.thickness = entity.thickness;
+            shape.vertices = entity.vertices;
+            continue;
+        }
+        let ghost = commands
+            .spawn((
+                CTransform::new(pos, Vec2::ZERO, entity.angle),
+                CShape {
+                    radius: entity.radius,
+                    color,
+                    outline,
+                    thickness: entity.thickness,
+                    vertices: entity.vertices,
+                },
+                TNetGhost,
+            ))
+            .id();
+        client.ghosts.insert(entity.id, ghost);
+    }
+    client.ghosts.retain(|id, ghost| {
+        let keep = seen.contains(id);
+        if !keep {
+            commands.entity(*ghost).despawn();
+        }
+        keep
+    });
+}
+
+fn s_link_flush(
+    server: Option<ResMut<NetServer>>,
+    client: Option<ResMut<NetClient>>,
+    time: Res<Time<Real>>,
+) {
+    let now = time.elapsed_seconds_f64();
+    if let Some(mut server) = server {
+        server.link.flush(now);
+    }
+    if let Some(mut client) = client {
+        client.link.flush(now);
+    }
+}
+
+fn s_client_bye(client: Res<NetClient>, mut app_exit_events: EventReader<bevy::app::AppExit>) {
+    if app_exit_events.read().next().is_none() || client.slot.is_none() {
+        return;
+    }
+    // sent straight away, the simulated network would hold it past the exit
+    let server = client.server;
+    if let Ok(bytes) = encode(&ClientMessage::Bye) {
+        if let Err(err) = client.link.socket.send_to(&bytes, server) {
+            error!("net.rs::s_client_bye: {}", err);
+        }
+    }
+}
+
+// endregion
+
+// region: functions
+
+impl Link {
+    pub fn bind(address: &str, net_config: &NetConfig) -> Result<Self, String> {
+        let socket = UdpSocket::bind(address).map_err(|err| format!("{}: {}", address, err))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|err| format!("{}: {}", address, err))?;
+        Ok(Self {
+            socket,
+            lag: net_config.lag.max(0.),
+            jitter: net_config.jitter.max(0.),
+            loss: net_config.loss.clamp(0., 1.),
+            rng: WyRand::from_entropy(),
+            delayed: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.local_addr().ok()
+    }
+
+    /// Sends `message` now, later or never, depending on the simulated network.
+    fn send(&mut self, now: f64, to: SocketAddr, message: &impl Serialize) {
+        if self.loss > 0. && self.rng.range_f32(0., 1.) < self.loss {
+            return;
+        }
+        let bytes = match encode(message) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!("net.rs::Link::send: {}", err);
+                return;
+            }
+        };
+        let delay = self.lag + self.rng.range_f32(0., 1.) * self.jitter;
+        if delay <= 0. {
+            self.send_now(to, &bytes);
+        } else {
+            self.delayed.push((now + delay as f64, to, bytes));
+        }
+    }
+
+    /// Sends the delayed packets whose time has come.
+    fn flush(&mut self, now: f64) {
+        let mut i = 0;
+        while i < self.delayed.len() {
+            if self.delayed[i].0 <= now {
+                let (_, to, bytes) = self.delayed.swap_remove(i);
+                self.send_now(to, &bytes);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn send_now(&self, to: SocketAddr, bytes: &[u8]) {
+        match self.socket.send_to(bytes, to) {
+            Ok(_) => (),
+            // nothing listening yet, the other side will ask again
+            Err(err) if err.kind() == ErrorKind::ConnectionRefused => (),
+            Err(err) => error!("net.rs::Link::send_now: {}: {}", to, err),
+        }
+    }
+
+    /// Everything that has arrived, skipping packets that don't parse.
+    fn receive<T: DeserializeOwned>(&mut self) -> Vec<(SocketAddr, T)> {
+        let mut messages = Vec::new();
+        let mut buf = [0u8; 65536];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => match decode(&buf[..len]) {
+                    Ok(message) => messages.push((from, message)),
+                    Err(err) => warn!("net.rs::Link::receive: {}: {}", from, err),
+                },
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // an earlier send found nothing listening
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => continue,
+                Err(err) => {
+                    error!("net.rs::Link::receive: {}", err);
+                    break;
+                }
+            }
+        }
+        messages
+    }
+}
+
+impl NetServer {
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.link.local_addr()
+    }
+}
+
+fn encode(message: &impl Serialize) -> Result<Vec<u8>, String> {
+    ron::to_string(message)
+        .map(String::into_bytes)
+        .map_err(|err| err.to_string())
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    let text = std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+    ron::from_str(text).map_err(|err| err.to_string())
+}
+
+// endregion
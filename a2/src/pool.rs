@@ -0,0 +1,95 @@
+// region: includes
+
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashSet};
+
+// endregion
+
+// region: Pool Structs
+
+/// Marks a pooled entity that is waiting to be reused. Gameplay and rendering queries skip it.
+///
+/// Sparse set storage so toggling it doesn't move the entity's other components between tables.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TInactive;
+
+/// Recycles entities of one kind instead of despawning and spawning them again.
+#[derive(Default, Debug)]
+pub struct EntityPool {
+    capacity: usize,
+    members: HashSet<Entity>,
+    free: Vec<Entity>,
+    /// the same entities as `free`, so a release can check for them without a scan
+    free_set: HashSet<Entity>,
+    /// entities spawned fresh, including ones past the capacity that won't be pooled
+    pub spawned: usize,
+    pub reused: usize,
+}
+
+/// Pools for the entities created most often, sized by `BulletConfig::pool_size` and
+/// `EnemyConfig::small_pool_size`.
+#[derive(Resource, Default, Debug)]
+pub struct Pools {
+    pub bullets: EntityPool,
+    pub fragments: EntityPool,
+}
+
+// endregion
+
+impl EntityPool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Reuses a free entity with `bundle` inserted over its old components, or spawns a new one.
+    /// Components that only some entities of the kind carry have to be removed by the caller.
+    pub fn spawn<'w, 's, 'a>(
+        &mut self,
+        commands: &'a mut Commands<'w, 's>,
+        bundle: impl Bundle,
+    ) -> EntityCommands<'w, 's, 'a> {
+        while let Some(e) = self.free.pop() {
+            self.free_set.remove(&e);
+            // a member despawned without going through the pool can't be reused
+            if commands.get_entity(e).is_none() {
+                self.members.remove(&e);
+                continue;
+            }
+            self.reused += 1;
+            let mut entity = commands.entity(e);
+            entity.remove::<TInactive>().insert(bundle);
+            return entity;
+        }
+        self.spawned += 1;
+        let entity = commands.spawn(bundle);
+        if self.members.len() < self.capacity {
+            self.members.insert(entity.id());
+        }
+        entity
+    }
+
+    /// Disables `e` for reuse if it belongs to the pool, returning false otherwise.
+    /// Releasing an entity twice in one tick is harmless.
+    pub fn release(&mut self, commands: &mut Commands, e: Entity) -> bool {
+        if !self.members.contains(&e) {
+            return false;
+        }
+        if self.free_set.insert(e) {
+            commands.entity(e).insert(TInactive);
+            self.free.push(e);
+        }
+        true
+    }
+}
+
+impl Pools {
+    /// Returns `e` to whichever pool it came from, or despawns it.
+    pub fn release(&mut self, commands: &mut Commands, e: Entity) {
+        if !self.bullets.release(commands, e) && !self.fragments.release(commands, e) {
+            commands.entity(e).despawn();
+        }
+    }
+}
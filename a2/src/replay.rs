@@ -0,0 +1,319 @@
+// region: includes
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use comp4300_common::rng::RngSeed;
+use serde::{Deserialize, Serialize};
+
+use crate::boss::BossSchedule;
+use crate::config::*;
+use crate::pool::{Pools, TInactive};
+use crate::{
+    s_enemy_spawner, s_shoot, spawn_players, AppState, Arena, CInput, CSlot, CTransform, Combo,
+    Focus, Players, TimeScale, TimeSinceSpawn,
+};
+
+// endregion
+
+// region: Replay Structs
+
+/// One player's input for one fixed gameplay tick. Shots are stored as aim directions.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct ReplayFrame {
+    pub up: bool,
+    pub left: bool,
+    pub right: bool,
+    pub down: bool,
+    pub shoot: Option<(f32, f32)>,
+    #[serde(default)]
+    pub held: Option<(f32, f32)>,
+    #[serde(default)]
+    pub cycle: i32,
+    #[serde(default)]
+    pub focus: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ReplayFile {
+    pub seed: u64,
+    /// the input of every player slot, by slot, for each tick
+    pub frames: Vec<Vec<ReplayFrame>>,
+}
+
+#[derive(Resource, Debug)]
+struct Replay {
+    file: ReplayFile,
+    // index of the tick currently being simulated
+    frame: usize,
+    speed: f32,
+    seek_target: Option<usize>,
+}
+
+// endregion
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Last,
+            s_replay_start
+                .run_if(in_state(AppState::Loading))
+                .run_if(resource_exists::<ReplayConfig>())
+                .run_if(resource_exists::<RngSeed>())
+                .run_if(not(resource_exists::<Replay>())),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                s_replay_record
+                    .before(s_shoot)
+                    .run_if(replay_mode(ReplayMode::Record)),
+                (
+                    s_replay_feed.before(s_shoot),
+                    s_replay_advance.after(s_enemy_spawner),
+                )
+                    .run_if(replay_mode(ReplayMode::Playback)),
+            )
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<Replay>()),
+        )
+        .add_systems(
+            Update,
+            (s_replay_controls, s_replay_seek)
+                .chain()
+                .run_if(replay_mode(ReplayMode::Playback))
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<Replay>()),
+        )
+        .add_systems(
+            Last,
+            s_replay_save
+                .run_if(replay_mode(ReplayMode::Record))
+                .run_if(in_state(AppState::InGame))
+                .run_if(resource_exists::<Replay>()),
+        );
+    }
+}
+
+pub fn replay_mode(mode: ReplayMode) -> impl FnMut(Option<Res<ReplayConfig>>) -> bool + Clone {
+    move |config: Option<Res<ReplayConfig>>| config.is_some_and(|c| c.mode == mode)
+}
+
+// region: systems
+
+/// Runs at the end of the loading frame so the first in-game tick is already seeded.
+fn s_replay_start(
+    mut commands: Commands,
+    replay_config: Res<ReplayConfig>,
+    rng_seed: Res<RngSeed>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    match replay_config.mode {
+        ReplayMode::Off => (),
+        ReplayMode::Record => {
+            let seed = rng_seed.0;
+            info!(
+                "Recording replay to {} with seed {}",
+                replay_config.file, seed
+            );
+            commands.insert_resource(Replay {
+                file: ReplayFile {
+                    seed,
+                    frames: Vec::new(),
+                },
+                frame: 0,
+                speed: 1.,
+                seek_target: None,
+            });
+        }
+        ReplayMode::Playback => {
+            let file = match read_replay(&replay_config.file) {
+                Ok(file) => file,
+                Err(err) => {
+                    error!("replay.rs::s_replay_start: {}", err);
+                    return;
+                }
+            };
+            // the recorded seed overrides the one picked from config or the command line
+            rng.reseed(file.seed.to_le_bytes());
+            commands.insert_resource(RngSeed(file.seed));
+            time.set_relative_speed(replay_config.speed);
+            info!(
+                "Playing back {} ({} ticks, seed {})",
+                replay_config.file,
+                file.frames.len(),
+                file.seed
+            );
+            commands.insert_resource(Replay {
+                file,
+                frame: 0,
+                speed: replay_config.speed,
+                seek_target: None,
+            });
+        }
+    }
+}
+
+fn s_replay_record(
+    mut replay: ResMut<Replay>,
+    players: Res<Players>,
+    input: Query<(&CSlot, &CInput)>,
+) {
+    // players waiting to respawn or out of lives keep an empty frame
+    let mut frames = vec![ReplayFrame::default(); players.0.len()];
+    for (slot, input) in input.iter() {
+        let Some(frame) = frames.get_mut(slot.0) else {
+            continue;
+        };
+        *frame = ReplayFrame {
+            up: input.up,
+            left: input.left,
+            right: input.right,
+            down: input.down,
+            shoot: input.shoot.map(|aim| (aim.x, aim.y)),
+            held: input.held.map(|aim| (aim.x, aim.y)),
+            cycle: input.cycle,
+            focus: input.focus,
+        };
+    }
+    replay.file.frames.push(frames);
+}
+
+fn s_replay_save(
+    replay: Res<Replay>,
+    replay_config: Res<ReplayConfig>,
+    mut app_exit_events: EventReader<bevy::app::AppExit>,
+) {
+    if app_exit_events.read().next().is_none() {
+        return;
+    }
+    match write_replay(&replay_config.file, &replay.file) {
+        Ok(()) => info!(
+            "Saved replay to {} ({} ticks)",
+            replay_config.file,
+            replay.file.frames.len()
+        ),
+        Err(err) => error!("replay.rs::s_replay_save: {}", err),
+    }
+}
+
+fn s_replay_feed(replay: Res<Replay>, mut input: Query<(&CSlot, &mut CInput)>) {
+    let Some(frames) = replay.file.frames.get(replay.frame) else {
+        return;
+    };
+    for (slot, mut input) in input.iter_mut() {
+        let Some(frame) = frames.get(slot.0) else {
+            continue;
+        };
+        input.up = frame.up;
+        input.left = frame.left;
+        input.right = frame.right;
+        input.down = frame.down;
+        input.shoot = frame.shoot.map(|(x, y)| Vec2::new(x, y));
+        input.held = frame.held.map(|(x, y)| Vec2::new(x, y));
+        input.cycle = frame.cycle;
+        input.focus = frame.focus;
+    }
+}
+
+fn s_replay_advance(mut replay: ResMut<Replay>, mut time: ResMut<Time<Virtual>>) {
+    if replay.frame >= replay.file.frames.len() {
+        return;
+    }
+    replay.frame += 1;
+    if replay.frame == replay.file.frames.len() {
+        info!("Replay finished after {} ticks", replay.frame);
+        time.set_relative_speed(1.);
+    }
+}
+
+fn s_replay_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut replay: ResMut<Replay>,
+    replay_config: Res<ReplayConfig>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if replay.frame >= replay.file.frames.len() {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Up) || keyboard.just_pressed(KeyCode::Down) {
+        replay.speed = if keyboard.just_pressed(KeyCode::Up) {
+            (replay.speed * 2.).min(16.)
+        } else {
+            (replay.speed / 2.).max(0.25)
+        };
+        time.set_relative_speed(replay.speed);
+        info!("Replay speed x{}", replay.speed);
+    }
+    if keyboard.just_pressed(KeyCode::Right) {
+        let last = replay.file.frames.len().saturating_sub(1);
+        replay.seek_target = Some((replay.frame + replay_config.seek_frames).min(last));
+    }
+    if keyboard.just_pressed(KeyCode::Left) {
+        replay.seek_target = Some(replay.frame.saturating_sub(replay_config.seek_frames));
+    }
+}
+
+/// Runs the fixed gameplay schedule back to back until the seek target is reached.
+fn s_replay_seek(world: &mut World) {
+    let Some(target) = world.resource_mut::<Replay>().seek_target.take() else {
+        return;
+    };
+    if target < world.resource::<Replay>().frame {
+        // seeking backwards replays the run from the start
+        world.run_system_once(replay_reset);
+    }
+    while world.resource::<Replay>().frame < target {
+        world.run_schedule(FixedUpdate);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn replay_reset(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    player_config: Res<PlayerConfig>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut players: ResMut<Players>,
+    mut time_since_spawn: ResMut<TimeSinceSpawn>,
+    mut combo: ResMut<Combo>,
+    mut time_scale: ResMut<TimeScale>,
+    mut focus: ResMut<Focus>,
+    mut pools: ResMut<Pools>,
+    entities: Query<Entity, (With<CTransform>, Without<TInactive>)>,
+    arena: Res<Arena>,
+) {
+    for e in entities.iter() {
+        pools.release(&mut commands, e);
+    }
+    time_since_spawn.0 = 0.;
+    *combo = Combo::default();
+    *time_scale = TimeScale::default();
+    *focus = Focus::default();
+    commands.insert_resource(BossSchedule::default());
+    rng.reseed(replay.file.seed.to_le_bytes());
+    spawn_players(&mut commands, &player_config, &arena, &mut players);
+    replay.frame = 0;
+}
+
+// endregion
+
+// region: functions
+
+pub fn read_replay(path: &str) -> Result<ReplayFile, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    ron::from_str(&text).map_err(|err| format!("{}: {}", path, err))
+}
+
+pub fn write_replay(path: &str, file: &ReplayFile) -> Result<(), String> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    let text = ron::ser::to_string_pretty(file, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("{}: {}", path, err))?;
+    std::fs::write(path, text).map_err(|err| format!("{}: {}", path, err))
+}
+
+// endregion
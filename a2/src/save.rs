@@ -0,0 +1,399 @@
+//! Quick save and load of the whole game to RON files, one per save slot. Loading restores
+//! the RNG too, so a loaded game carries on exactly as the saved one would have.
+
+// region: includes
+
+use bevy::prelude::*;
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use serde::{Deserialize, Serialize};
+
+use crate::boss::{BossSchedule, CBoss, CStruck};
+use crate::config::SaveConfig;
+use crate::pool::{Pools, TInactive};
+use crate::{
+    net, AppState, CBounces, CBoundary, CCollision, CCooldown, CHealth, CHoming, CInput, CLifespan,
+    COwner, CPierce, CScore, CShape, CSlot, CTelegraph, CTransform, CWeapon, Combo, Focus, Players,
+    TBullet, TEnemy, TFragment, THostile, TPlayer, TPopup, TimeScale, TimeSinceSpawn,
+};
+
+// endregion
+
+// region: Save Structs
+
+/// The tag an entity carries.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+enum SavedTag {
+    Enemy,
+    Bullet,
+    Player,
+    Popup(u32),
+    /// bosses and their projectiles
+    Hostile,
+    Fragment,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntity {
+    transform: CTransform,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tag: Option<SavedTag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shape: Option<CShape>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    collision: Option<CCollision>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    boundary: Option<CBoundary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lifespan: Option<CLifespan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<CScore>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    telegraph: Option<CTelegraph>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pierce: Option<CPierce>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    homing: Option<CHoming>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bounces: Option<CBounces>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<COwner>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slot: Option<CSlot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weapon: Option<CWeapon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cooldown: Option<CCooldown>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    input: Option<CInput>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    health: Option<CHealth>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    boss: Option<CBoss>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    rng: GlobalEntropy<WyRand>,
+    players: Players,
+    combo: Combo,
+    time_since_spawn: TimeSinceSpawn,
+    bosses: BossSchedule,
+    time_scale: TimeScale,
+    focus: Focus,
+    entities: Vec<SavedEntity>,
+}
+
+/// Saves the game to, or loads it from, a save slot at the end of the frame.
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaveRequest {
+    Save(usize),
+    Load(usize),
+}
+
+/// The slot F5 and F9 save to and load from.
+#[derive(Resource, Default, Debug)]
+struct SaveSlot(usize);
+
+type SavedQuery = (
+    &'static CTransform,
+    (
+        Option<&'static CShape>,
+        Option<&'static CCollision>,
+        Option<&'static CBoundary>,
+        Option<&'static CLifespan>,
+        Option<&'static CScore>,
+        Option<&'static CTelegraph>,
+    ),
+    (
+        Option<&'static CPierce>,
+        Option<&'static CHoming>,
+        Option<&'static CBounces>,
+        Option<&'static COwner>,
+    ),
+    (
+        Option<&'static CSlot>,
+        Option<&'static CWeapon>,
+        Option<&'static CCooldown>,
+        Option<&'static CInput>,
+    ),
+    (
+        Has<TEnemy>,
+        Has<TBullet>,
+        Has<TPlayer>,
+        Option<&'static TPopup>,
+        Has<THostile>,
+        Has<TFragment>,
+    ),
+    (Option<&'static CHealth>, Option<&'static CBoss>),
+);
+
+// endregion
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveRequest>()
+            .init_resource::<SaveSlot>()
+            .add_systems(
+                Update,
+                (
+                    s_save_keys.run_if(resource_exists::<Input<KeyCode>>()),
+                    s_save,
+                    s_load,
+                )
+                    .chain()
+                    .run_if(resource_exists::<SaveConfig>())
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(net::simulates),
+            );
+    }
+}
+
+// region: systems
+
+fn s_save_keys(
+    keyboard: Res<Input<KeyCode>>,
+    mut slot: ResMut<SaveSlot>,
+    save_config: Res<SaveConfig>,
+    mut requests: EventWriter<SaveRequest>,
+) {
+    if keyboard.just_pressed(KeyCode::F6) {
+        slot.0 = (slot.0 + 1) % save_config.slots.max(1);
+        info!("Save slot {}", slot.0 + 1);
+    }
+    if keyboard.just_pressed(KeyCode::F5) {
+        requests.send(SaveRequest::Save(slot.0));
+    }
+    if keyboard.just_pressed(KeyCode::F9) {
+        requests.send(SaveRequest::Load(slot.0));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_save(
+    mut requests: EventReader<SaveRequest>,
+    save_config: Res<SaveConfig>,
+    rng: Res<GlobalEntropy<WyRand>>,
+    players: Res<Players>,
+    combo: Res<Combo>,
+    time_since_spawn: Res<TimeSinceSpawn>,
+    bosses: Res<BossSchedule>,
+    time_scale: Res<TimeScale>,
+    focus: Res<Focus>,
+    query: Query<SavedQuery, Without<TInactive>>,
+) {
+    for request in requests.read() {
+        let SaveRequest::Save(slot) = *request else {
+            continue;
+        };
+        let file = SaveFile {
+            rng: rng.clone(),
+            players: players.clone(),
+            combo: combo.clone(),
+            time_since_spawn: time_since_spawn.clone(),
+            bosses: bosses.clone(),
+            time_scale: time_scale.clone(),
+            focus: focus.clone(),
+            entities: query.iter().map(save_entity).collect(),
+        };
+        let path = slot_path(&save_config, slot);
+        match write_save(&path, &file) {
+            Ok(()) => info!("Saved {} entities to {}", file.entities.len(), path),
+            Err(err) => error!("save.rs::s_save: {}", err),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_load(
+    mut commands: Commands,
+    mut requests: EventReader<SaveRequest>,
+    save_config: Res<SaveConfig>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    mut players: ResMut<Players>,
+    mut combo: ResMut<Combo>,
+    mut time_since_spawn: ResMut<TimeSinceSpawn>,
+    mut bosses: ResMut<BossSchedule>,
+    mut time_scale: ResMut<TimeScale>,
+    mut focus: ResMut<Focus>,
+    mut pools: ResMut<Pools>,
+    entities: Query<Entity, (With<CTransform>, Without<TInactive>)>,
+) {
+    // only the last load of the frame matters
+    let Some(slot) = requests
+        .read()
+        .filter_map(|request| match request {
+            SaveRequest::Load(slot) => Some(*slot),
+            SaveRequest::Save(_) => None,
+        })
+        .last()
+    else {
+        return;
+    };
+    let path = slot_path(&save_config, slot);
+    let file = match read_save(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            error!("save.rs::s_load: {}", err);
+            return;
+        }
+    };
+
+    for e in entities.iter() {
+        pools.release(&mut commands, e);
+    }
+    *rng = file.rng;
+    *players = file.players;
+    *combo = file.combo;
+    *time_since_spawn = file.time_since_spawn;
+    *bosses = file.bosses;
+    *time_scale = file.time_scale;
+    *focus = file.focus;
+    let count = file.entities.len();
+    for saved in file.entities {
+        load_entity(&mut commands, &mut pools, saved);
+    }
+    info!("Loaded {} entities from {}", count, path);
+}
+
+// endregion
+
+// region: functions
+
+fn save_entity(
+    (
+        tf,
+        (shape, collision, boundary, lifespan, score, telegraph),
+        (pierce, homing, bounces, owner),
+        (slot, weapon, cooldown, input),
+        (enemy, bullet, player, popup, hostile, fragment),
+        (health, boss),
+    ): bevy::ecs::query::QueryItem<SavedQuery>,
+) -> SavedEntity {
+    let tag = match (enemy, bullet, player, popup, hostile, fragment) {
+        (true, _, _, _, _, _) => Some(SavedTag::Enemy),
+        (_, true, _, _, _, _) => Some(SavedTag::Bullet),
+        (_, _, true, _, _, _) => Some(SavedTag::Player),
+        (_, _, _, Some(popup), _, _) => Some(SavedTag::Popup(popup.0)),
+        (_, _, _, _, true, _) => Some(SavedTag::Hostile),
+        (_, _, _, _, _, true) => Some(SavedTag::Fragment),
+        _ => None,
+    };
+    SavedEntity {
+        transform: tf.clone(),
+        tag,
+        shape: shape.cloned(),
+        collision: collision.cloned(),
+        boundary: boundary.cloned(),
+        lifespan: lifespan.cloned(),
+        score: score.cloned(),
+        telegraph: telegraph.cloned(),
+        pierce: pierce.cloned(),
+        homing: homing.cloned(),
+        bounces: bounces.cloned(),
+        owner: owner.cloned(),
+        slot: slot.cloned(),
+        weapon: weapon.cloned(),
+        cooldown: cooldown.cloned(),
+        input: input.cloned(),
+        health: health.cloned(),
+        boss: boss.cloned(),
+    }
+}
+
+/// Spawns a saved entity, bullets and fragments through their pools.
+fn load_entity(commands: &mut Commands, pools: &mut Pools, saved: SavedEntity) {
+    let mut e = match saved.tag {
+        Some(SavedTag::Bullet) => pools.bullets.spawn(commands, (saved.transform, TBullet)),
+        Some(SavedTag::Fragment) => pools
+            .fragments
+            .spawn(commands, (saved.transform, TFragment)),
+        _ => commands.spawn(saved.transform),
+    };
+    // a reused entity may still carry the extras of what it was before
+    e.remove::<(CTelegraph, CPierce, CHoming, CBounces, CStruck)>();
+    match saved.tag {
+        Some(SavedTag::Enemy) => {
+            e.insert(TEnemy);
+        }
+        Some(SavedTag::Player) => {
+            e.insert(TPlayer);
+        }
+        Some(SavedTag::Popup(points)) => {
+            e.insert(TPopup(points));
+        }
+        Some(SavedTag::Hostile) => {
+            e.insert(THostile);
+        }
+        Some(SavedTag::Bullet) | Some(SavedTag::Fragment) | None => (),
+    }
+    if let Some(c) = saved.shape {
+        e.insert(c);
+    }
+    if let Some(c) = saved.collision {
+        e.insert(c);
+    }
+    if let Some(c) = saved.boundary {
+        e.insert(c);
+    }
+    if let Some(c) = saved.lifespan {
+        e.insert(c);
+    }
+    if let Some(c) = saved.score {
+        e.insert(c);
+    }
+    if let Some(c) = saved.telegraph {
+        e.insert(c);
+    }
+    if let Some(c) = saved.pierce {
+        e.insert(c);
+    }
+    if let Some(c) = saved.homing {
+        e.insert(c);
+    }
+    if let Some(c) = saved.bounces {
+        e.insert(c);
+    }
+    if let Some(c) = saved.owner {
+        e.insert(c);
+    }
+    if let Some(c) = saved.slot {
+        e.insert(c);
+    }
+    if let Some(c) = saved.weapon {
+        e.insert(c);
+    }
+    if let Some(c) = saved.cooldown {
+        e.insert(c);
+    }
+    if let Some(c) = saved.input {
+        e.insert(c);
+    }
+    if let Some(c) = saved.health {
+        e.insert(c);
+    }
+    if let Some(c) = saved.boss {
+        e.insert(c);
+    }
+}
+
+pub fn slot_path(save_config: &SaveConfig, slot: usize) -> String {
+    format!("{}/slot{}.save.ron", save_config.dir, slot + 1)
+}
+
+pub fn read_save(path: &str) -> Result<SaveFile, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    ron::from_str(&text).map_err(|err| format!("{}: {}", path, err))
+}
+
+pub fn write_save(path: &str, file: &SaveFile) -> Result<(), String> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    let text = ron::ser::to_string_pretty(file, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("{}: {}", path, err))?;
+    std::fs::write(path, text).map_err(|err| format!("{}: {}", path, err))
+}
+
+// endregion
@@ -0,0 +1,649 @@
+//! In-game settings screen, opened and closed with F1. It edits `WindowConfig`, `FontConfig`
+//! and the keyboard bindings in `PlayerConfig`, applies them as they change and writes them
+//! back to their RON files on closing. Only the values that changed are rewritten, so the
+//! comments and any fields the game doesn't know about are kept.
+
+// region: includes
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::{prelude::*, window::WindowMode};
+use comp4300_common::cli::Cli;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::config::{Controls, FontConfig, PlayerConfig, WindowConfig};
+use crate::{rgb, AppState, TScoreText};
+
+// endregion
+
+const RESOLUTIONS: [(f32, f32); 5] = [
+    (800., 600.),
+    (1024., 768.),
+    (1280., 720.),
+    (1600., 900.),
+    (1920., 1080.),
+];
+const FRAME_LIMITS: [f32; 5] = [30., 60., 120., 144., 240.];
+const MIN_FONT_SIZE: f32 = 8.;
+const MAX_FONT_SIZE: f32 = 96.;
+const FONT_STEP: f32 = 2.;
+
+// region: Settings Structs
+
+/// Whether the settings screen is open, which pauses the game, and the row selected on it.
+#[derive(Resource, Default, Debug)]
+pub struct SettingsMenu {
+    pub open: bool,
+    selected: usize,
+    // waiting for the key to bind to the selected row
+    rebinding: bool,
+}
+
+/// A keyboard control of one player slot.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Shoot,
+    PrevWeapon,
+    NextWeapon,
+    Focus,
+}
+
+impl Action {
+    const ALL: [Action; 8] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Shoot,
+        Action::PrevWeapon,
+        Action::NextWeapon,
+        Action::Focus,
+    ];
+
+    /// The field holding this action's key in `Controls`.
+    fn field(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Shoot => "shoot",
+            Action::PrevWeapon => "prev_weapon",
+            Action::NextWeapon => "next_weapon",
+            Action::Focus => "focus",
+        }
+    }
+
+    /// The key bound to this action, None for gamepads and the mouse button.
+    fn key(self, controls: &mut Controls) -> Option<&mut KeyCode> {
+        match controls {
+            Controls::KeyboardMouse {
+                up,
+                down,
+                left,
+                right,
+                prev_weapon,
+                next_weapon,
+                focus,
+            } => match self {
+                Action::Up => Some(up),
+                Action::Down => Some(down),
+                Action::Left => Some(left),
+                Action::Right => Some(right),
+                Action::Shoot => None,
+                Action::PrevWeapon => Some(prev_weapon),
+                Action::NextWeapon => Some(next_weapon),
+                Action::Focus => Some(focus),
+            },
+            Controls::Keyboard {
+                up,
+                down,
+                left,
+                right,
+                shoot,
+                prev_weapon,
+                next_weapon,
+                focus,
+            } => match self {
+                Action::Up => Some(up),
+                Action::Down => Some(down),
+                Action::Left => Some(left),
+                Action::Right => Some(right),
+                Action::Shoot => Some(shoot),
+                Action::PrevWeapon => Some(prev_weapon),
+                Action::NextWeapon => Some(next_weapon),
+                Action::Focus => Some(focus),
+            },
+            Controls::Gamepad(_) => None,
+        }
+    }
+
+    fn bound(self, mut controls: Controls) -> Option<KeyCode> {
+        self.key(&mut controls).copied()
+    }
+}
+
+/// One row of the settings screen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Setting {
+    Resolution,
+    FrameLimit,
+    Fullscreen,
+    FontSize,
+    Binding { slot: usize, action: Action },
+}
+
+/// A step into a RON value: a struct field or a sequence element.
+#[derive(Clone, Copy, Debug)]
+pub enum RonKey<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+#[derive(Component)]
+struct TSettingsText;
+
+// endregion
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsMenu>()
+            .add_systems(OnEnter(AppState::InGame), s_setup_settings_text)
+            .add_systems(
+                Update,
+                (
+                    (s_settings_input, s_settings_text).chain(),
+                    s_apply_window.run_if(resource_exists_and_changed::<WindowConfig>()),
+                    s_apply_font.run_if(resource_exists_and_changed::<FontConfig>()),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Whether the settings screen is closed, so the game runs. True when there is no menu.
+pub fn closed(menu: Option<Res<SettingsMenu>>) -> bool {
+    !menu.is_some_and(|m| m.open)
+}
+
+// region: systems
+
+fn s_setup_settings_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font_config: Res<FontConfig>,
+) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(font_config.file.clone()),
+                    font_size: font_config.size / 2.,
+                    color: rgb(font_config.color),
+                },
+            )
+            .with_alignment(TextAlignment::Left),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        TSettingsText,
+    ));
+}
+
+fn s_settings_input(
+    keyboard: Res<Input<KeyCode>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut window_config: ResMut<WindowConfig>,
+    mut font_config: ResMut<FontConfig>,
+    mut player_config: ResMut<PlayerConfig>,
+    mut time: ResMut<Time<Virtual>>,
+    cli: Res<Cli>,
+) {
+    if menu.rebinding {
+        if keyboard.just_pressed(KeyCode::Escape) {
+            menu.rebinding = false;
+        } else if let Some(&pressed) = keyboard.get_just_pressed().next() {
+            let rows = settings(&player_config);
+            if let Some(Setting::Binding { slot, action }) = rows.get(menu.selected).copied() {
+                if let Some(key) = action.key(&mut player_config.slots[slot].controls) {
+                    *key = pressed;
+                }
+            }
+            menu.rebinding = false;
+        }
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::F1) {
+        menu.open = !menu.open;
+        // fixed ticks follow virtual time, so this pauses the game under the menu
+        if menu.open {
+            time.pause();
+        } else {
+            time.unpause();
+            match write_settings(&cli, &window_config, &font_config, &player_config) {
+                Ok(()) => info!("Settings saved"),
+                Err(err) => error!("settings.rs::s_settings_input: {}", err),
+            }
+        }
+        return;
+    }
+    if !menu.open {
+        return;
+    }
+
+    let rows = settings(&player_config);
+    if keyboard.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + rows.len() - 1) % rows.len();
+    }
+    if keyboard.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % rows.len();
+    }
+    let step =
+        keyboard.just_pressed(KeyCode::Right) as i32 - keyboard.just_pressed(KeyCode::Left) as i32;
+    let enter = keyboard.just_pressed(KeyCode::Return);
+    match rows[menu.selected.min(rows.len() - 1)] {
+        Setting::Resolution if step != 0 => {
+            window_config.size = cycle(&RESOLUTIONS, window_config.size, step);
+        }
+        Setting::FrameLimit if step != 0 => {
+            window_config.frame_limit = cycle(&FRAME_LIMITS, window_config.frame_limit, step);
+        }
+        Setting::Fullscreen if step != 0 || enter => {
+            window_config.fullscreen = !window_config.fullscreen;
+        }
+        Setting::FontSize if step != 0 => {
+            font_config.size =
+                (font_config.size + step as f32 * FONT_STEP).clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+        }
+        Setting::Binding { .. } if enter => menu.rebinding = true,
+        _ => (),
+    }
+}
+
+fn s_settings_text(
+    menu: Res<SettingsMenu>,
+    window_config: Res<WindowConfig>,
+    font_config: Res<FontConfig>,
+    player_config: Res<PlayerConfig>,
+    windows: Query<&Window>,
+    mut text: Query<(&mut Text, &mut Transform, &mut Visibility), With<TSettingsText>>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = text.get_single_mut() else {
+        return;
+    };
+    if !menu.open {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+    if let Ok(window) = windows.get_single() {
+        transform.translation = Vec3::new(window.width() / 2., window.height() / 2., 2.);
+    }
+
+    let mut lines = vec!["Settings (F1 to save and close)".to_string(), String::new()];
+    for (i, setting) in settings(&player_config).into_iter().enumerate() {
+        let value = match setting {
+            Setting::Resolution => format!(
+                "Resolution: {}x{}",
+                window_config.size.0, window_config.size.1
+            ),
+            Setting::FrameLimit => format!("Frame limit: {}", window_config.frame_limit),
+            Setting::Fullscreen => format!("Fullscreen: {}", window_config.fullscreen),
+            Setting::FontSize => format!("Font size: {}", font_config.size),
+            Setting::Binding { .. } if menu.rebinding && i == menu.selected => {
+                format!("{}press a key", binding_label(setting))
+            }
+            Setting::Binding { slot, action } => {
+                let key = action.bound(player_config.slots[slot].controls);
+                format!(
+                    "{}{:?}",
+                    binding_label(setting),
+                    key.unwrap_or(KeyCode::Unlabeled)
+                )
+            }
+        };
+        let cursor = if i == menu.selected { "> " } else { "  " };
+        lines.push(format!("{}{}", cursor, value));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+fn s_apply_window(
+    window_config: Res<WindowConfig>,
+    mut framepace_settings: ResMut<bevy_framepace::FramepaceSettings>,
+    mut windows: Query<&mut Window>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    window
+        .resolution
+        .set(window_config.size.0, window_config.size.1);
+    window.mode = if window_config.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+
+    use bevy_framepace::Limiter;
+    framepace_settings.limiter = Limiter::from_framerate(window_config.frame_limit.into());
+}
+
+fn s_apply_font(
+    font_config: Res<FontConfig>,
+    mut hud: Query<&mut Text, (With<TScoreText>, Without<TSettingsText>)>,
+    mut settings_text: Query<&mut Text, With<TSettingsText>>,
+) {
+    for mut text in hud.iter_mut() {
+        text.sections[0].style.font_size = font_config.size;
+    }
+    // drawn at half size, like the debug panel
+    for mut text in settings_text.iter_mut() {
+        text.sections[0].style.font_size = font_config.size / 2.;
+    }
+}
+
+// endregion
+
+// region: helper functions
+
+/// Every row of the settings screen: the window and font, then each key of every slot played
+/// with a keyboard.
+fn settings(player_config: &PlayerConfig) -> Vec<Setting> {
+    let mut rows = vec![
+        Setting::Resolution,
+        Setting::FrameLimit,
+        Setting::Fullscreen,
+        Setting::FontSize,
+    ];
+    for (slot, player_slot) in player_config.slots.iter().enumerate() {
+        for action in Action::ALL {
+            if action.bound(player_slot.controls).is_some() {
+                rows.push(Setting::Binding { slot, action });
+            }
+        }
+    }
+    rows
+}
+
+/// The option `step` places after `current`, or the first one when `current` isn't listed.
+fn cycle<T: Copy + PartialEq>(options: &[T], current: T, step: i32) -> T {
+    let len = options.len() as i32;
+    match options.iter().position(|o| *o == current) {
+        Some(i) => options[(i as i32 + step).rem_euclid(len) as usize],
+        None => options[0],
+    }
+}
+
+fn binding_label(setting: Setting) -> String {
+    match setting {
+        Setting::Binding { slot, action } => format!("P{} {}: ", slot + 1, action.field()),
+        _ => String::new(),
+    }
+}
+
+/// Where a config file loaded from the config directory lives on disk.
+fn config_path(cli: &Cli, file: &str) -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(cli.config_path(file))
+}
+
+fn write_settings(
+    cli: &Cli,
+    window_config: &WindowConfig,
+    font_config: &FontConfig,
+    player_config: &PlayerConfig,
+) -> Result<(), String> {
+    edit_file(&config_path(cli, "config.window.ron"), |text| {
+        set_field(text, &[RonKey::Field("size")], &window_config.size)?;
+        set_field(
+            text,
+            &[RonKey::Field("frame_limit")],
+            &window_config.frame_limit,
+        )?;
+        set_field(
+            text,
+            &[RonKey::Field("fullscreen")],
+            &window_config.fullscreen,
+        )
+    })?;
+    edit_file(&config_path(cli, "config.font.ron"), |text| {
+        set_field(text, &[RonKey::Field("size")], &font_config.size)
+    })?;
+    edit_file(&config_path(cli, "config.player.ron"), |text| {
+        for (slot, player_slot) in player_config.slots.iter().enumerate() {
+            for action in Action::ALL {
+                if let Some(key) = action.bound(player_slot.controls) {
+                    let path = [
+                        RonKey::Field("slots"),
+                        RonKey::Index(slot),
+                        RonKey::Field("controls"),
+                        RonKey::Field(action.field()),
+                    ];
+                    set_field(text, &path, &key)?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Rewrites the file at `path` with `edit`, leaving it untouched when nothing changed.
+fn edit_file(
+    path: &Path,
+    edit: impl FnOnce(&mut String) -> Result<(), String>,
+) -> Result<(), String> {
+    let display = path.display();
+    let old = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", display, err))?;
+    let mut text = old.clone();
+    edit(&mut text).map_err(|err| format!("{}: {}", display, err))?;
+    if text != old {
+        std::fs::write(path, text).map_err(|err| format!("{}: {}", display, err))?;
+    }
+    Ok(())
+}
+
+/// Sets the field at the end of `path` in the RON `text` to `value`, touching nothing else.
+/// A value that already reads as `value` is kept as written, and a missing field is added at
+/// the end of its struct.
+pub fn set_field<T: Serialize + DeserializeOwned + PartialEq>(
+    text: &mut String,
+    path: &[RonKey],
+    value: &T,
+) -> Result<(), String> {
+    let Some((RonKey::Field(name), parent)) = path.split_last() else {
+        return Err("the path has to end in a field".to_string());
+    };
+    let mut start = 0;
+    for key in parent {
+        start = find_key(text.as_bytes(), start, *key)?.start;
+    }
+    let new = ron::to_string(value).map_err(|err| err.to_string())?;
+    match find_field(text.as_bytes(), start, name)? {
+        Ok(field) => {
+            if ron::from_str::<T>(&text[field.clone()]).ok().as_ref() != Some(value) {
+                text.replace_range(field, &new);
+            }
+        }
+        Err(close) => {
+            let last = last_token_end(text.as_bytes(), start, close);
+            let field = match text.as_bytes()[last - 1] {
+                b'(' => format!("{}: {}", name, new),
+                b',' => format!(" {}: {}", name, new),
+                _ => format!(", {}: {}", name, new),
+            };
+            text.insert_str(last, &field);
+        }
+    }
+    Ok(())
+}
+
+/// The span of the field or element `key` of the value starting at `start`.
+fn find_key(text: &[u8], start: usize, key: RonKey) -> Result<Range<usize>, String> {
+    match key {
+        RonKey::Field(name) => {
+            find_field(text, start, name)?.map_err(|_| format!("no field `{}`", name))
+        }
+        RonKey::Index(index) => {
+            let open = skip_blank(text, start);
+            if text.get(open) != Some(&b'[') {
+                return Err(format!("expected a list for element {}", index));
+            }
+            let mut i = open + 1;
+            for n in 0.. {
+                let element = skip_blank(text, i);
+                if matches!(text.get(element), None | Some(b']')) {
+                    break;
+                }
+                let (end, stop) = scan_value(text, element);
+                if n == index {
+                    return Ok(element..end);
+                }
+                if text.get(stop) != Some(&b',') {
+                    break;
+                }
+                i = stop + 1;
+            }
+            Err(format!("no element {}", index))
+        }
+    }
+}
+
+/// The span of the value of field `name` in the struct starting at `start`, or the index of
+/// the struct's closing paren when it has no such field.
+fn find_field(
+    text: &[u8],
+    start: usize,
+    name: &str,
+) -> Result<Result<Range<usize>, usize>, String> {
+    // the struct's name is optional
+    let open = skip_blank(text, skip_ident(text, skip_blank(text, start)));
+    if text.get(open) != Some(&b'(') {
+        return Err(format!("expected a struct for field `{}`", name));
+    }
+    let mut i = open + 1;
+    loop {
+        let ident = skip_blank(text, i);
+        match text.get(ident) {
+            Some(b')') => return Ok(Err(ident)),
+            None => return Err("unclosed struct".to_string()),
+            _ => (),
+        }
+        let ident_end = skip_ident(text, ident);
+        let colon = skip_blank(text, ident_end);
+        if text.get(colon) != Some(&b':') {
+            return Err(format!(
+                "expected `:` after `{}`",
+                String::from_utf8_lossy(&text[ident..ident_end])
+            ));
+        }
+        let value = skip_blank(text, colon + 1);
+        let (end, stop) = scan_value(text, value);
+        if &text[ident..ident_end] == name.as_bytes() {
+            return Ok(Ok(value..end));
+        }
+        match text.get(stop) {
+            Some(b',') => i = stop + 1,
+            Some(b')') => return Ok(Err(stop)),
+            _ => return Err("unclosed struct".to_string()),
+        }
+    }
+}
+
+/// Reads the value starting at `start`, returning where it ends, without the blanks and
+/// comments after it, and the index of the `,` or closing bracket that follows.
+fn scan_value(text: &[u8], start: usize) -> (usize, usize) {
+    let mut depth = 0;
+    let mut i = start;
+    let mut end = start;
+    while i < text.len() {
+        let next = skip_blank(text, i);
+        if next != i {
+            i = next;
+            continue;
+        }
+        match text[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' if depth == 0 => break,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => break,
+            b'"' => i = skip_string(text, i),
+            _ => (),
+        }
+        i += 1;
+        end = i;
+    }
+    (end, i)
+}
+
+/// Just past the last character in `start..end` that isn't blank or in a comment.
+fn last_token_end(text: &[u8], start: usize, end: usize) -> usize {
+    let mut i = start;
+    let mut last = start;
+    while i < end {
+        let next = skip_blank(text, i);
+        if next != i {
+            i = next;
+            continue;
+        }
+        if text[i] == b'"' {
+            i = skip_string(text, i);
+        }
+        i += 1;
+        last = i;
+    }
+    last
+}
+
+/// The index of the closing quote of the string opening at `i`.
+fn skip_string(text: &[u8], mut i: usize) -> usize {
+    i += 1;
+    while i < text.len() && text[i] != b'"' {
+        if text[i] == b'\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    i.min(text.len() - 1)
+}
+
+fn skip_ident(text: &[u8], mut i: usize) -> usize {
+    while i < text.len() && (text[i].is_ascii_alphanumeric() || text[i] == b'_') {
+        i += 1;
+    }
+    i
+}
+
+/// The first index from `i` that isn't whitespace or in a comment.
+fn skip_blank(text: &[u8], mut i: usize) -> usize {
+    loop {
+        while i < text.len() && text[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if text[i..].starts_with(b"//") {
+            while i < text.len() && text[i] != b'\n' {
+                i += 1;
+            }
+        } else if text[i..].starts_with(b"/*") {
+            i = text[i + 2..]
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(text.len(), |p| i + p + 4);
+        } else {
+            return i;
+        }
+    }
+}
+
+// endregion
@@ -0,0 +1,331 @@
+//! Gameplay statistics kept over every session in one RON file, and the achievements from
+//! `StatsConfig` unlocked from them, each shown as a toast when it unlocks.
+
+// region: includes
+
+use std::collections::BTreeMap;
+
+use bevy::{app::AppExit, prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{FontConfig, Goal, ReplayMode, StatsConfig};
+use crate::replay::replay_mode;
+use crate::{net, s_telegraph, AppState, Arena, CSlot, Players, TPlayer};
+
+// endregion
+
+// region: Stats Structs
+
+/// Something a player did that the stats count.
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameEvent {
+    Shot {
+        slot: usize,
+        bullet: Entity,
+    },
+    /// `bullet` struck an enemy, piercing bullets can strike more than one
+    Hit {
+        slot: usize,
+        bullet: Entity,
+    },
+    Kill {
+        slot: usize,
+        vertices: u32,
+    },
+    Death {
+        slot: usize,
+    },
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct AchievementUnlocked {
+    pub name: String,
+    pub description: String,
+}
+
+/// Totals over every session, for all players together.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Stats {
+    pub shots: u64,
+    /// shots that struck at least one enemy
+    pub hits: u64,
+    /// enemies killed by their vertex count
+    pub kills: BTreeMap<u32, u64>,
+    pub deaths: u64,
+    /// longest any player stayed alive, in seconds
+    pub longest_life: f32,
+    pub peak_score: u32,
+    /// names of the achievements unlocked
+    pub unlocked: Vec<String>,
+}
+
+impl Stats {
+    /// Percent of shots that struck an enemy.
+    pub fn accuracy(&self) -> f32 {
+        if self.shots == 0 {
+            return 0.;
+        }
+        self.hits as f32 / self.shots as f32 * 100.
+    }
+
+    /// Kills of enemies with `vertices` vertices, or of any enemy.
+    pub fn kills_of(&self, vertices: Option<u32>) -> u64 {
+        match vertices {
+            Some(vertices) => self.kills.get(&vertices).copied().unwrap_or(0),
+            None => self.kills.values().sum(),
+        }
+    }
+
+    pub fn reached(&self, goal: &Goal) -> bool {
+        match *goal {
+            Goal::Kills { vertices, count } => self.kills_of(vertices) >= count,
+            Goal::Shots(count) => self.shots >= count,
+            Goal::Hits(count) => self.hits >= count,
+            Goal::Accuracy { percent, shots } => self.shots >= shots && self.accuracy() >= percent,
+            Goal::Survive(seconds) => self.longest_life >= seconds,
+            Goal::Score(score) => self.peak_score >= score,
+            Goal::Deaths(count) => self.deaths >= count,
+        }
+    }
+}
+
+/// What the stats need from this session only.
+#[derive(Resource, Default, Debug)]
+struct StatsTracker {
+    /// seconds each slot has been alive since it last died
+    alive: Vec<f32>,
+    /// bullets in flight that already struck an enemy, so piercing doesn't count twice
+    struck: HashSet<Entity>,
+    /// a death or an unlock happened since the stats were last written
+    save_due: bool,
+}
+
+#[derive(Component)]
+struct TToast {
+    remaining: f32,
+}
+
+// endregion
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GameEvent>()
+            .add_event::<AchievementUnlocked>()
+            .init_resource::<Stats>()
+            .init_resource::<StatsTracker>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                s_load_stats.run_if(resource_exists::<StatsConfig>()),
+            )
+            .add_systems(
+                FixedUpdate,
+                (s_track_stats, s_achievements)
+                    .chain()
+                    .after(s_telegraph)
+                    .run_if(resource_exists::<StatsConfig>())
+                    .run_if(in_state(AppState::InGame))
+                    .run_if(net::simulates)
+                    // a replay is a game already counted
+                    .run_if(not(replay_mode(ReplayMode::Playback))),
+            )
+            .add_systems(
+                Last,
+                s_save_stats
+                    .run_if(resource_exists::<StatsConfig>())
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Shows unlocked achievements, only added with a window.
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            s_toasts
+                .run_if(resource_exists::<StatsConfig>())
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// region: systems
+
+fn s_load_stats(mut stats: ResMut<Stats>, stats_config: Res<StatsConfig>) {
+    match read_stats(&stats_config.file) {
+        Ok(Some(loaded)) => *stats = loaded,
+        Ok(None) => (),
+        Err(err) => error!("stats.rs::s_load_stats: {}", err),
+    }
+}
+
+fn s_track_stats(
+    mut events: EventReader<GameEvent>,
+    mut stats: ResMut<Stats>,
+    mut tracker: ResMut<StatsTracker>,
+    players: Res<Players>,
+    alive: Query<&CSlot, With<TPlayer>>,
+    time: Res<Time<Fixed>>,
+) {
+    let tracker = tracker.as_mut();
+    tracker.alive.resize(players.0.len(), 0.);
+    for event in events.read() {
+        match *event {
+            GameEvent::Shot { bullet, .. } => {
+                // pooled bullets come back as new shots
+                tracker.struck.remove(&bullet);
+                stats.shots += 1;
+            }
+            GameEvent::Hit { bullet, .. } => {
+                if tracker.struck.insert(bullet) {
+                    stats.hits += 1;
+                }
+            }
+            GameEvent::Kill { vertices, .. } => *stats.kills.entry(vertices).or_default() += 1,
+            GameEvent::Death { slot } => {
+                stats.deaths += 1;
+                if let Some(alive) = tracker.alive.get_mut(slot) {
+                    *alive = 0.;
+                }
+                tracker.save_due = true;
+            }
+        }
+    }
+
+    for slot in alive.iter() {
+        if let Some(alive) = tracker.alive.get_mut(slot.0) {
+            *alive += time.delta_seconds();
+            if *alive > stats.longest_life {
+                stats.longest_life = *alive;
+            }
+        }
+    }
+    if let Some(score) = players.0.iter().map(|p| p.score).max() {
+        if score > stats.peak_score {
+            stats.peak_score = score;
+        }
+    }
+}
+
+fn s_achievements(
+    mut stats: ResMut<Stats>,
+    mut tracker: ResMut<StatsTracker>,
+    stats_config: Res<StatsConfig>,
+    mut unlocks: EventWriter<AchievementUnlocked>,
+) {
+    for achievement in stats_config.achievements.iter() {
+        if stats.unlocked.contains(&achievement.name) || !stats.reached(&achievement.goal) {
+            continue;
+        }
+        info!(
+            "Achievement unlocked: {} - {}",
+            achievement.name, achievement.description
+        );
+        stats.unlocked.push(achievement.name.clone());
+        unlocks.send(AchievementUnlocked {
+            name: achievement.name.clone(),
+            description: achievement.description.clone(),
+        });
+        tracker.save_due = true;
+    }
+}
+
+fn s_save_stats(
+    stats: Res<Stats>,
+    mut tracker: ResMut<StatsTracker>,
+    stats_config: Res<StatsConfig>,
+    exit: EventReader<AppExit>,
+) {
+    if !tracker.save_due && exit.is_empty() {
+        return;
+    }
+    tracker.save_due = false;
+    if let Err(err) = write_stats(&stats_config.file, &stats) {
+        error!("stats.rs::s_save_stats: {}", err);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_toasts(
+    mut commands: Commands,
+    mut unlocks: EventReader<AchievementUnlocked>,
+    mut toasts: Query<(Entity, &mut TToast, &mut Transform, &mut Text)>,
+    asset_server: Res<AssetServer>,
+    font_config: Res<FontConfig>,
+    stats_config: Res<StatsConfig>,
+    arena: Res<Arena>,
+    time: Res<Time>,
+) {
+    for unlock in unlocks.read() {
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!(
+                        "Achievement unlocked: {}\n{}",
+                        unlock.name, unlock.description
+                    ),
+                    TextStyle {
+                        font: asset_server.load(font_config.file.clone()),
+                        font_size: font_config.size * 0.75,
+                        color: Color::GOLD,
+                    },
+                )
+                .with_alignment(TextAlignment::Center),
+                text_anchor: bevy::sprite::Anchor::TopCenter,
+                ..Default::default()
+            },
+            TToast {
+                remaining: stats_config.toast_time,
+            },
+        ));
+    }
+
+    // stacked down from the top, the oldest first, fading out over their last second
+    let mut line = 0.;
+    for (e, mut toast, mut transform, mut text) in toasts.iter_mut() {
+        toast.remaining -= time.delta_seconds();
+        if toast.remaining <= 0. {
+            commands.entity(e).despawn();
+            continue;
+        }
+        transform.translation = Vec3::new(
+            arena.width / 2.,
+            arena.height - 16. - line * font_config.size * 2.,
+            2.,
+        );
+        text.sections[0].style.color.set_a(toast.remaining.min(1.));
+        line += 1.;
+    }
+}
+
+// endregion
+
+// region: functions
+
+/// The stats kept in `path`, or None when nothing was kept yet.
+pub fn read_stats(path: &str) -> Result<Option<Stats>, String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("{}: {}", path, err)),
+    };
+    ron::from_str(&text)
+        .map(Some)
+        .map_err(|err| format!("{}: {}", path, err))
+}
+
+pub fn write_stats(path: &str, stats: &Stats) -> Result<(), String> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    let text = ron::ser::to_string_pretty(stats, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("{}: {}", path, err))?;
+    std::fs::write(path, text).map_err(|err| format!("{}: {}", path, err))
+}
+
+// endregion
@@ -0,0 +1,146 @@
+//! Opt-in per-frame telemetry from `TelemetryConfig`, for profiling enemy floods: frame time,
+//! entity counts by tag, collisions, spawns and despawns, score and state.
+
+// region: includes
+
+use bevy::{app::AppExit, ecs::entity::Entities, prelude::*};
+use comp4300_common::telemetry::{CollisionCounts, TelemetryWriter, Value};
+
+use crate::boss::CBoss;
+use crate::config::TelemetryConfig;
+use crate::pool::TInactive;
+use crate::{AppState, CTransform, Players, TBullet, TEnemy, THostile, TPlayer, TPopup};
+
+// endregion
+
+// rows buffered between writes to disk
+const FLUSH_FRAMES: u64 = 60;
+
+// region: Telemetry Structs
+
+/// The open telemetry file, only there while telemetry is enabled and the file could be created.
+#[derive(Resource)]
+struct Telemetry {
+    writer: TelemetryWriter,
+    frame: u64,
+}
+
+// endregion
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            s_open_telemetry.run_if(resource_exists::<TelemetryConfig>()),
+        )
+        .add_systems(
+            Last,
+            s_telemetry
+                .run_if(resource_exists::<Telemetry>())
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// region: systems
+
+fn s_open_telemetry(mut commands: Commands, telemetry_config: Res<TelemetryConfig>) {
+    match TelemetryWriter::create(&telemetry_config.file, telemetry_config.format) {
+        Ok(writer) => {
+            info!("Writing telemetry to {}", telemetry_config.file);
+            commands.insert_resource(Telemetry { writer, frame: 0 });
+        }
+        Err(err) => error!("telemetry.rs::s_open_telemetry: {}", err),
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn s_telemetry(
+    mut commands: Commands,
+    mut telemetry: ResMut<Telemetry>,
+    mut collisions: ResMut<CollisionCounts>,
+    entities: &Entities,
+    tags: Query<
+        (
+            Has<TPlayer>,
+            Has<TEnemy>,
+            Has<TBullet>,
+            Has<THostile>,
+            Has<TPopup>,
+            Has<CBoss>,
+        ),
+        Without<TInactive>,
+    >,
+    // pooled entities come and go by `TInactive` rather than by spawning and despawning
+    spawned: Query<(), (Added<CTransform>, Without<TInactive>)>,
+    mut reused: RemovedComponents<TInactive>,
+    mut despawned: RemovedComponents<CTransform>,
+    released: Query<(), Added<TInactive>>,
+    players: Res<Players>,
+    state: Res<State<AppState>>,
+    time: Res<Time<Real>>,
+    virtual_time: Res<Time<Virtual>>,
+    exit: EventReader<AppExit>,
+) {
+    let mut counts = [0usize; 6];
+    for (player, enemy, bullet, hostile, popup, boss) in tags.iter() {
+        for (count, has) in counts
+            .iter_mut()
+            .zip([player, enemy, bullet, hostile, popup, boss])
+        {
+            *count += has as usize;
+        }
+    }
+    let [players_alive, enemies, bullets, hostiles, popups, bosses] = counts;
+    let state = if virtual_time.is_paused() {
+        "Paused".to_string()
+    } else {
+        format!("{:?}", state.get())
+    };
+
+    let row: &[(&'static str, Value)] = &[
+        ("frame", telemetry.frame.into()),
+        ("frame_time_ms", (time.delta_seconds_f64() * 1000.).into()),
+        ("state", state.into()),
+        ("entities", entities.len().into()),
+        ("players", players_alive.into()),
+        ("enemies", enemies.into()),
+        ("bullets", bullets.into()),
+        ("hostiles", hostiles.into()),
+        ("popups", popups.into()),
+        ("bosses", bosses.into()),
+        ("collisions_checked", collisions.checked.into()),
+        ("collisions_resolved", collisions.resolved.into()),
+        (
+            "spawned",
+            (spawned.iter().count() + reused.read().count()).into(),
+        ),
+        (
+            "despawned",
+            (despawned.read().count() + released.iter().count()).into(),
+        ),
+        (
+            "score",
+            players.0.iter().map(|p| p.score as u64).sum::<u64>().into(),
+        ),
+    ];
+    *collisions = CollisionCounts::default();
+    telemetry.frame += 1;
+
+    let result = telemetry.writer.write_row(row).and_then(|()| {
+        if telemetry.frame.is_multiple_of(FLUSH_FRAMES) || !exit.is_empty() {
+            telemetry.writer.flush()
+        } else {
+            Ok(())
+        }
+    });
+    if let Err(err) = result {
+        // one error is enough, stop writing instead of repeating it every frame
+        error!("telemetry.rs::s_telemetry: {}", err);
+        commands.remove_resource::<Telemetry>();
+    }
+}
+
+// endregion
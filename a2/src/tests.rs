@@ -0,0 +1,1288 @@
+use bevy::{app::AppExit, prelude::*};
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use comp4300_common::telemetry::TelemetryFormat;
+
+use crate::boss::{BossSchedule, CBoss};
+use crate::collision::{is_collision, polygon, polygon_circle_overlap, polygons_overlap};
+use crate::config::{
+    Achievement, BossPhase, BossType, BoundaryMode, BulletType, CollisionMode, Crowding,
+    Difficulty, Goal, Milestone, NetConfig, NetMode, Pattern, ReplayConfig, ReplayMode,
+    TelemetryConfig,
+};
+use crate::harness::{Configs, FrameInput, TestApp};
+use crate::mesh::{outline_mesh, polygon_corners, polygon_mesh};
+use crate::net::NetServer;
+use crate::pool::{Pools, TInactive};
+use crate::replay::read_replay;
+use crate::save::SaveRequest;
+use crate::settings::{set_field, RonKey};
+use crate::stats::{read_stats, Stats};
+use crate::{
+    CCollision, CCooldown, CHealth, CPierce, CScore, CShape, CTelegraph, CTransform, Combo, Focus,
+    Players, TBullet, TEnemy, TFragment, THostile, TPlayer, TPopup, TimeScale, TimeSinceSpawn,
+};
+
+/// A game whose only weapons are the default pistol changed by each of `edits`, in order.
+fn with_weapons(edits: &[fn(&mut BulletType)]) -> TestApp {
+    let mut configs = Configs::default();
+    let pistol = configs.bullet.types[0].clone();
+    configs.bullet.types = edits
+        .iter()
+        .map(|edit| {
+            let mut bullet = pistol.clone();
+            edit(&mut bullet);
+            bullet
+        })
+        .collect();
+    TestApp::with_configs(configs)
+}
+
+fn click(pos: Vec2) -> FrameInput {
+    FrameInput {
+        click: Some(pos),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn player_spawns_in_the_center() {
+    let mut game = TestApp::new();
+    assert_eq!(game.player_pos(), Vec2::new(640., 360.));
+}
+
+#[test]
+fn bullet_fired_at_enemy_scores_its_value() {
+    let mut game = TestApp::new();
+    let enemy = game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 6);
+    let value = game.app.world.get::<CScore>(enemy).unwrap().0;
+
+    // the enemy sits on the player's right, so aim straight across the window
+    game.step_with(FrameInput {
+        click: Some(Vec2::new(1000., 360.)),
+        ..Default::default()
+    });
+    assert_eq!(game.count::<TBullet>(), 1);
+
+    game.run(60);
+    assert_eq!(game.count::<TEnemy>(), 0);
+    assert_eq!(game.count::<TBullet>(), 0);
+    assert_eq!(game.score(), value);
+}
+
+#[test]
+fn bullet_fired_away_from_enemy_misses() {
+    let mut game = TestApp::new();
+    game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 6);
+
+    game.step_with(FrameInput {
+        click: Some(Vec2::new(100., 360.)),
+        ..Default::default()
+    });
+    game.run(60);
+    assert_eq!(game.count::<TEnemy>(), 1);
+    assert_eq!(game.score(), 0);
+}
+
+#[test]
+fn player_cannot_leave_the_window() {
+    let mut game = TestApp::new();
+    let corners = [vec![KeyCode::W, KeyCode::D], vec![KeyCode::S, KeyCode::A]];
+    for keys in corners {
+        for _ in 0..240 {
+            game.step_with(FrameInput {
+                keys: keys.clone(),
+                ..Default::default()
+            });
+            let pos = game.player_pos();
+            assert!(
+                pos.x >= 32. && pos.x <= 1280. - 32.,
+                "x out of bounds: {}",
+                pos.x
+            );
+            assert!(
+                pos.y >= 32. && pos.y <= 720. - 32.,
+                "y out of bounds: {}",
+                pos.y
+            );
+        }
+    }
+}
+
+#[test]
+fn touching_an_enemy_costs_a_life_and_keeps_the_score() {
+    let mut game = TestApp::new();
+    game.app.world.resource_mut::<Players>().0[0].score = 500;
+    game.spawn_enemy(Vec2::new(740., 360.), Vec2::ZERO, 4);
+
+    game.run_script(60, |_| FrameInput {
+        keys: vec![KeyCode::D],
+        ..Default::default()
+    });
+    assert_eq!(game.count::<TEnemy>(), 0);
+    assert_eq!(game.count::<TPlayer>(), 1);
+    assert_eq!(game.lives_of(0), 2);
+    assert!(game.score() >= 500);
+}
+
+#[test]
+fn losing_the_last_life_restarts_with_no_score() {
+    let mut configs = Configs::default();
+    configs.player.lives = 1;
+    let mut game = TestApp::with_configs(configs);
+    game.app.world.resource_mut::<Players>().0[0].score = 500;
+    game.spawn_enemy(Vec2::new(740., 360.), Vec2::ZERO, 4);
+
+    game.run_script(60, |_| FrameInput {
+        keys: vec![KeyCode::D],
+        ..Default::default()
+    });
+    assert_eq!(game.count::<TEnemy>(), 0);
+    assert_eq!(game.count::<TPlayer>(), 1);
+    assert_eq!(game.lives_of(0), 1);
+    assert_eq!(game.score(), 0);
+}
+
+#[test]
+fn quick_kills_raise_the_multiplier() {
+    let mut game = TestApp::new();
+    game.spawn_enemy(Vec2::new(900., 360.), Vec2::ZERO, 4);
+    game.spawn_enemy(Vec2::new(1150., 360.), Vec2::ZERO, 5);
+
+    // the second shot is fired once the first enemy is gone and lands inside the combo window
+    game.run_script(90, |frame| FrameInput {
+        click: (frame == 0 || frame == 30).then_some(Vec2::new(1150., 360.)),
+        ..Default::default()
+    });
+    assert_eq!(game.count::<TEnemy>(), 0);
+    assert_eq!(game.score(), 400 + 750);
+    assert_eq!(game.app.world.resource::<Combo>().multiplier, 2.);
+    assert!(game.count::<TPopup>() > 0);
+}
+
+#[test]
+fn spread_weapon_fires_its_bullet_count() {
+    let mut game = with_weapons(&[|b| {
+        b.count = 5;
+        b.spread = 40.;
+    }]);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    assert_eq!(game.count::<TBullet>(), 5);
+}
+
+#[test]
+fn piercing_bullet_kills_enemies_in_a_line() {
+    let mut game = with_weapons(&[|b| b.pierce = 1]);
+    game.spawn_enemy(Vec2::new(850., 360.), Vec2::ZERO, 3);
+    game.spawn_enemy(Vec2::new(1050., 360.), Vec2::ZERO, 3);
+
+    game.step_with(click(Vec2::new(1050., 360.)));
+    game.run(60);
+    assert_eq!(game.count::<TEnemy>(), 0);
+}
+
+#[test]
+fn fire_rate_limits_clicks_and_cycling_switches_weapon() {
+    let mut game = with_weapons(&[|b| b.fire_rate = 1., |b| b.shape_radius = 4.]);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.step();
+    game.step_with(click(Vec2::new(1000., 360.)));
+    assert_eq!(game.count::<TBullet>(), 1);
+
+    game.step();
+    game.step_with(FrameInput {
+        keys: vec![KeyCode::E],
+        click: Some(Vec2::new(1000., 360.)),
+    });
+    let radii: Vec<f32> = game
+        .app
+        .world
+        .query_filtered::<&CShape, With<TBullet>>()
+        .iter(&game.app.world)
+        .map(|sh| sh.radius)
+        .collect();
+    assert_eq!(radii.len(), 2);
+    assert!(radii.contains(&4.));
+}
+
+#[test]
+fn holding_fire_repeats_at_the_fire_rate() {
+    let mut game = with_weapons(&[|b| {
+        b.fire_rate = 10.;
+        // keep every bullet on screen for the count
+        b.boundary = Some(BoundaryMode::Bounce);
+    }]);
+    // one second of holding the button down
+    game.run_script(60, |_| click(Vec2::new(640., 0.)));
+    assert_eq!(game.count::<TBullet>(), 10);
+}
+
+#[test]
+fn holding_fire_with_unlimited_rate_fires_once() {
+    let mut game = TestApp::new();
+    game.run_script(30, |_| click(Vec2::new(640., 0.)));
+    assert_eq!(game.count::<TBullet>(), 1);
+}
+
+#[test]
+fn bullets_despawn_after_leaving_the_window() {
+    let mut game = TestApp::new();
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(30);
+    assert_eq!(game.count::<TBullet>(), 1);
+    // 650 units to be fully outside at 600 per second, well within the lifespan
+    game.run(40);
+    assert_eq!(game.count::<TBullet>(), 0);
+}
+
+#[test]
+fn wrapping_enemy_comes_back_on_the_other_side() {
+    let mut configs = Configs::default();
+    configs.enemy.boundary = BoundaryMode::Wrap;
+    let mut game = TestApp::with_configs(configs);
+    let enemy = game.spawn_enemy(Vec2::new(1200., 100.), Vec2::new(600., 0.), 3);
+
+    game.run(20);
+    let pos = game.pos(enemy);
+    assert!(pos.x < 640., "expected the enemy on the left, at {}", pos.x);
+    assert_eq!(pos.y, 100.);
+}
+
+#[test]
+fn expired_bullets_are_reused() {
+    let mut game = TestApp::new();
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(70);
+    assert_eq!(game.count::<TBullet>(), 0);
+
+    game.step_with(click(Vec2::new(1000., 360.)));
+    assert_eq!(game.count::<TBullet>(), 1);
+    let pools = game.app.world.resource::<Pools>();
+    assert_eq!(pools.bullets.spawned, 1);
+    assert_eq!(pools.bullets.reused, 1);
+}
+
+#[test]
+fn bullets_despawned_outside_the_pool_are_not_reused() {
+    let mut game = TestApp::new();
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(70);
+    let bullet = game
+        .app
+        .world
+        .query_filtered::<Entity, With<TBullet>>()
+        .single(&game.app.world);
+    game.app.world.despawn(bullet);
+
+    game.step_with(click(Vec2::new(1000., 360.)));
+    assert_eq!(game.count::<TBullet>(), 1);
+    let pools = game.app.world.resource::<Pools>();
+    assert_eq!(pools.bullets.spawned, 2);
+    assert_eq!(pools.bullets.reused, 0);
+}
+
+/// Heavy fire into a stream of enemies, with and without pooling. Not a correctness test, run it
+/// with `cargo test --release heavy_fire -- --ignored --nocapture` to compare the numbers.
+#[test]
+#[ignore]
+fn heavy_fire_benchmark() {
+    for pool_size in [0, 1024] {
+        let mut configs = Configs::default();
+        configs.bullet.pool_size = pool_size;
+        configs.bullet.types[0].count = 24;
+        configs.bullet.types[0].spread = 360.;
+        configs.bullet.types[0].fire_rate = 30.;
+        configs.enemy.small_pool_size = pool_size;
+        configs.enemy.spawn_interval = 0.05;
+        let mut game = TestApp::with_configs(configs);
+
+        let start = std::time::Instant::now();
+        game.run_script(3600, |_| click(Vec2::new(1000., 360.)));
+        let elapsed = start.elapsed();
+
+        let world = &game.app.world;
+        let pools = world.resource::<Pools>();
+        println!(
+            "pool size {}: {:?} for 3600 ticks, bullets spawned {} reused {}, \
+             fragments spawned {} reused {}, {} archetypes, {} tables, {} entities",
+            pool_size,
+            elapsed,
+            pools.bullets.spawned,
+            pools.bullets.reused,
+            pools.fragments.spawned,
+            pools.fragments.reused,
+            world.archetypes().len(),
+            world.storages().tables.len(),
+            world.entities().len(),
+        );
+    }
+}
+
+#[test]
+fn shrinking_the_window_pulls_entities_back_in() {
+    let mut game = TestApp::new();
+    let enemy = game.spawn_enemy(Vec2::new(1200., 650.), Vec2::ZERO, 3);
+
+    let mut windows = game.app.world.query::<&mut Window>();
+    windows
+        .single_mut(&mut game.app.world)
+        .resolution
+        .set(800., 600.);
+    game.step();
+
+    assert_eq!(game.pos(enemy), Vec2::new(800. - 32., 600. - 32.));
+}
+
+#[test]
+fn enemies_spawn_away_from_the_player() {
+    let mut configs = Configs::default();
+    configs.enemy.spawn_interval = 0.05;
+    configs.enemy.min_speed = 0.;
+    configs.enemy.max_speed = 0.;
+    let mut game = TestApp::with_configs(configs);
+    game.run(120);
+
+    let player = game.player_pos();
+    let mut enemies = game.app.world.query_filtered::<&CTransform, With<TEnemy>>();
+    assert!(enemies.iter(&game.app.world).count() > 10);
+    for tf in enemies.iter(&game.app.world) {
+        assert!(
+            tf.pos.distance(player) >= 200.,
+            "enemy spawned at {}",
+            tf.pos
+        );
+    }
+}
+
+#[test]
+fn telegraphed_enemy_collides_once_it_appears_and_player_respawns_safely() {
+    let mut game = TestApp::new();
+    let spawning = game.spawn_enemy(Vec2::new(640., 360.), Vec2::ZERO, 3);
+    game.app.world.entity_mut(spawning).insert(CTelegraph {
+        remaining: 0.5,
+        total: 0.5,
+    });
+    game.spawn_enemy(Vec2::new(200., 360.), Vec2::ZERO, 3);
+
+    game.run(20);
+    let player = game.player();
+    assert_eq!(game.pos(player), Vec2::new(640., 360.));
+
+    game.run(20);
+    assert_ne!(game.player(), player);
+    assert!(game.player_pos().distance(Vec2::new(200., 360.)) > 600.);
+}
+
+fn co_op(players: usize) -> Configs {
+    let mut configs = Configs::default();
+    configs.player.count = players;
+    configs
+}
+
+#[test]
+fn co_op_players_spawn_spread_out_with_their_own_colors() {
+    let mut game = TestApp::with_configs(co_op(2));
+    assert_eq!(game.count::<TPlayer>(), 2);
+    let p1 = game.player_in(0).unwrap();
+    let p2 = game.player_in(1).unwrap();
+    assert_eq!(game.pos(p1), Vec2::new(1280. / 3., 360.));
+    assert_eq!(game.pos(p2), Vec2::new(1280. * 2. / 3., 360.));
+    let color = |e| game.app.world.get::<CShape>(e).unwrap().color;
+    assert_ne!(color(p1), color(p2));
+}
+
+#[test]
+fn kills_are_credited_to_the_player_who_fired() {
+    let mut game = TestApp::with_configs(co_op(2));
+    let p2 = game.player_in(1).unwrap();
+    let above = game.pos(p2) + Vec2::new(0., 250.);
+    let enemy = game.spawn_enemy(above, Vec2::ZERO, 5);
+    let value = game.app.world.get::<CScore>(enemy).unwrap().0;
+
+    // keyboard-only players fire the way they last moved, up to begin with
+    game.step_with(FrameInput {
+        keys: vec![KeyCode::ControlRight],
+        ..Default::default()
+    });
+    game.run(60);
+    assert_eq!(game.count::<TEnemy>(), 0);
+    assert_eq!(game.score_of(0), 0);
+    assert_eq!(game.score_of(1), value);
+}
+
+#[test]
+fn bullets_pass_through_other_players() {
+    let mut game = TestApp::with_configs(co_op(2));
+    let p2 = game.player_in(1).unwrap();
+    // aim at the second player, clicks are in window coordinates with y down
+    game.step_with(click(Vec2::new(1280. * 2. / 3., 360.)));
+    game.run(60);
+    assert_eq!(game.player_in(1), Some(p2));
+    assert_eq!(game.lives_of(1), 3);
+}
+
+#[test]
+fn player_out_of_lives_sits_out_until_everyone_is() {
+    let mut configs = co_op(2);
+    configs.player.lives = 1;
+    let mut game = TestApp::with_configs(configs);
+    let p1 = game.player_in(0).unwrap();
+    let pos = game.pos(p1);
+    game.spawn_enemy(pos, Vec2::ZERO, 3);
+    game.step();
+    assert_eq!(game.player_in(0), None);
+    assert_eq!(game.count::<TPlayer>(), 1);
+
+    let p2 = game.player_in(1).unwrap();
+    let pos = game.pos(p2);
+    game.spawn_enemy(pos, Vec2::ZERO, 3);
+    game.step();
+    assert_eq!(game.count::<TPlayer>(), 2);
+    assert_eq!(game.lives_of(0), 1);
+    assert_eq!(game.lives_of(1), 1);
+}
+
+fn net(mode: NetMode, address: String, lag: f32) -> NetConfig {
+    NetConfig {
+        mode,
+        address,
+        interpolation_delay: 0.1,
+        lag,
+        jitter: 0.,
+        loss: 0.,
+    }
+}
+
+/// A two player server on a free localhost port and a client joining it.
+fn server_and_client(lag: f32) -> (TestApp, TestApp) {
+    let mut configs = co_op(2);
+    configs.net = Some(net(NetMode::Server, "127.0.0.1:0".to_string(), lag));
+    let server = TestApp::with_configs(configs);
+    let address = server
+        .app
+        .world
+        .resource::<NetServer>()
+        .local_addr()
+        .unwrap();
+
+    let configs = Configs {
+        net: Some(net(NetMode::Client, address.to_string(), lag)),
+        ..Default::default()
+    };
+    (server, TestApp::with_configs(configs))
+}
+
+#[test]
+fn client_moves_its_player_on_the_server_and_mirrors_the_world() {
+    let (mut server, mut client) = server_and_client(0.);
+    let remote = server.player_in(1).unwrap();
+    let start = server.pos(remote);
+    for _ in 0..60 {
+        client.step_with(FrameInput {
+            keys: vec![KeyCode::D],
+            ..Default::default()
+        });
+        server.step();
+    }
+    let (host, remote) = (server.player_in(0).unwrap(), server.player_in(1).unwrap());
+    assert!(server.pos(remote).x > start.x);
+    assert_eq!(server.pos(host), Vec2::new(1280. / 3., 360.));
+    // the client runs no game of its own, it only draws the server's two players
+    assert_eq!(client.count::<TPlayer>(), 0);
+    assert_eq!(client.count::<CShape>(), 2);
+    assert_eq!(client.app.world.resource::<Players>().0.len(), 2);
+}
+
+#[test]
+fn simulated_lag_holds_packets_back() {
+    let (mut server, mut client) = server_and_client(0.25);
+    for _ in 0..10 {
+        client.step();
+        server.step();
+    }
+    assert_eq!(client.count::<CShape>(), 0);
+    for _ in 0..80 {
+        client.step();
+        server.step();
+    }
+    assert_eq!(client.count::<CShape>(), 2);
+}
+
+/// Everything that should come out the same when a game is replayed from a save.
+fn world_state(game: &mut TestApp) -> (Vec<(f32, f32)>, usize, u32, f32) {
+    let mut enemies: Vec<(f32, f32)> = game
+        .app
+        .world
+        .query_filtered::<&CTransform, With<TEnemy>>()
+        .iter(&game.app.world)
+        .map(|tf| (tf.pos.x, tf.pos.y))
+        .collect();
+    enemies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let bullets = game.count::<TBullet>();
+    let time_since_spawn = game.app.world.resource::<TimeSinceSpawn>().0;
+    (enemies, bullets, game.score(), time_since_spawn)
+}
+
+#[test]
+fn loading_a_save_carries_on_exactly_like_the_saved_game() {
+    let mut configs = Configs::default();
+    configs.enemy.spawn_interval = 0.3;
+    let mut game = TestApp::with_configs(configs);
+    game.spawn_enemy(Vec2::new(1000., 360.), Vec2::new(-60., 90.), 5);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(20);
+
+    game.app.world.send_event(SaveRequest::Save(0));
+    game.step();
+    game.run(60);
+    let saved_run = world_state(&mut game);
+    assert!(saved_run.0.len() >= 2);
+
+    game.app.world.send_event(SaveRequest::Load(0));
+    game.step();
+    game.run(60);
+    assert_eq!(world_state(&mut game), saved_run);
+}
+
+/// The mesh's triangles as corner positions.
+fn triangles(mesh: &Mesh) -> Vec<[Vec2; 3]> {
+    let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        panic!("mesh has no positions");
+    };
+    let corner = |i: usize| Vec2::new(positions[i][0], positions[i][1]);
+    let indices: Vec<usize> = mesh.indices().unwrap().iter().collect();
+    indices
+        .chunks(3)
+        .map(|t| [corner(t[0]), corner(t[1]), corner(t[2])])
+        .collect()
+}
+
+/// Twice the signed area, positive for counter-clockwise triangles.
+fn signed_area([a, b, c]: [Vec2; 3]) -> f32 {
+    (b - a).perp_dot(c - a) / 2.
+}
+
+#[test]
+fn shape_meshes_face_the_camera_and_cover_the_polygon() {
+    let polygon_area = |radius: f32, vertices: u32| {
+        let corners = polygon_corners(radius, vertices);
+        (0..corners.len())
+            .map(|i| corners[i].perp_dot(corners[(i + 1) % corners.len()]).abs() / 2.)
+            .sum::<f32>()
+    };
+
+    let fill = triangles(&polygon_mesh(32., 5));
+    assert_eq!(fill.len(), 5);
+    assert!(fill.iter().all(|t| signed_area(*t) > 0.));
+    let area: f32 = fill.iter().map(|t| signed_area(*t)).sum();
+    assert!((area - polygon_area(32., 5)).abs() < 0.01);
+
+    // the band is as wide as the thickness along every edge
+    let outline = triangles(&outline_mesh(32., 4, 3.));
+    assert_eq!(outline.len(), 8);
+    assert!(outline.iter().all(|t| signed_area(*t) > 0.));
+    let area: f32 = outline.iter().map(|t| signed_area(*t)).sum();
+    let side = 32. * 2f32.sqrt();
+    assert!((area - ((side + 6.).powi(2) - side.powi(2))).abs() < 0.01);
+}
+
+#[test]
+fn shape_meshes_line_up_with_the_collision_polygon() {
+    let shape = CShape {
+        radius: 20.,
+        color: Color::WHITE,
+        outline: Color::WHITE,
+        thickness: 2.,
+        vertices: 6,
+    };
+    let tf = CTransform::new(Vec2::ZERO, Vec2::ZERO, 0.);
+    for (mesh, collision) in polygon_corners(20., 6)
+        .into_iter()
+        .zip(polygon(&tf, &shape))
+    {
+        assert!(mesh.distance(collision) < 0.001);
+    }
+}
+
+#[test]
+fn stats_count_a_piercing_shot_as_one_hit_and_every_kill() {
+    let mut configs = Configs::default();
+    configs.bullet.types[0].pierce = 1;
+    let mut game = TestApp::with_configs(configs);
+    game.spawn_enemy(Vec2::new(900., 360.), Vec2::ZERO, 8);
+    game.spawn_enemy(Vec2::new(1100., 360.), Vec2::ZERO, 3);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(60);
+
+    let stats = game.app.world.resource::<Stats>();
+    assert_eq!(stats.shots, 1);
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.accuracy(), 100.);
+    assert_eq!(stats.kills_of(Some(8)), 1);
+    assert_eq!(stats.kills_of(Some(3)), 1);
+    assert_eq!(stats.peak_score, game.score());
+}
+
+#[test]
+fn dying_is_counted_with_the_longest_life_and_saved() {
+    let configs = Configs::default();
+    let file = configs.stats.file.clone();
+    let mut game = TestApp::with_configs(configs);
+    game.run(30);
+    game.spawn_enemy(Vec2::new(740., 360.), Vec2::ZERO, 4);
+    game.run_script(60, |_| FrameInput {
+        keys: vec![KeyCode::D],
+        ..Default::default()
+    });
+
+    // written at the death, the respawned player has been alive a while since
+    let saved = read_stats(&file).unwrap().unwrap();
+    assert_eq!(saved.deaths, 1);
+    assert!(saved.longest_life > 0.5);
+    assert_eq!(game.app.world.resource::<Stats>().deaths, 1);
+}
+
+#[test]
+fn achievements_unlock_once_and_carry_over_to_the_next_session() {
+    let mut configs = Configs::default();
+    configs.stats.achievements = vec![Achievement {
+        name: "First Blood".to_string(),
+        description: "Destroy an enemy".to_string(),
+        goal: Goal::Kills {
+            vertices: None,
+            count: 1,
+        },
+    }];
+    let achievements = configs.stats.achievements.clone();
+    let file = configs.stats.file.clone();
+    let mut game = TestApp::with_configs(configs);
+    game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 5);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(60);
+    game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 5);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    game.run(60);
+    assert_eq!(
+        game.app.world.resource::<Stats>().unlocked,
+        vec!["First Blood".to_string()]
+    );
+
+    let mut configs = Configs::default();
+    configs.stats.file = file;
+    configs.stats.achievements = achievements;
+    let game = TestApp::with_configs(configs);
+    let stats = game.app.world.resource::<Stats>();
+    assert_eq!(stats.unlocked, vec!["First Blood".to_string()]);
+    assert_eq!(stats.kills_of(None), 1);
+}
+
+/// A boss that appears at `milestone` and sits still, one phase per pattern taking over at the
+/// health fraction given with it.
+fn boss(milestone: Milestone, health: u32, phases: &[(f32, Pattern)]) -> BossType {
+    BossType {
+        name: "Test".to_string(),
+        milestone,
+        shape_radius: 64.,
+        collision_radius: 64.,
+        color: (1., 0., 1.),
+        outline_color: (1., 1., 1.),
+        outline_thickness: 4.,
+        vertices: 6,
+        health,
+        score: 5000,
+        explosion_rings: 2,
+        phases: phases
+            .iter()
+            .map(|(health, pattern)| BossPhase {
+                health: *health,
+                pattern: *pattern,
+                fire_rate: 1.,
+                projectile_speed: 10.,
+                speed: 0.,
+            })
+            .collect(),
+    }
+}
+
+fn boss_entity(game: &mut TestApp) -> Option<Entity> {
+    game.app
+        .world
+        .query_filtered::<Entity, With<CBoss>>()
+        .iter(&game.app.world)
+        .next()
+}
+
+#[test]
+fn boss_appears_once_at_its_score_milestone() {
+    let mut configs = Configs::default();
+    configs.boss.bosses = vec![boss(
+        Milestone::Score(1000),
+        10,
+        &[(1., Pattern::Ring { count: 8 })],
+    )];
+    let mut game = TestApp::with_configs(configs);
+    game.app.world.resource_mut::<Players>().0[0].score = 999;
+    game.run(5);
+    assert_eq!(game.count::<CBoss>(), 0);
+
+    game.app.world.resource_mut::<Players>().0[0].score = 1000;
+    game.run(5);
+    let boss = boss_entity(&mut game).unwrap();
+    // away from the player, who is in the middle
+    assert!(game.pos(boss).distance(game.player_pos()) > 300.);
+
+    game.app.world.despawn(boss);
+    game.run(5);
+    assert_eq!(game.count::<CBoss>(), 0);
+}
+
+#[test]
+fn boss_waves_count_spawned_enemies_and_pause_spawning() {
+    let mut configs = Configs::default();
+    configs.enemy.spawn_interval = 0.1;
+    configs.boss.wave_size = 2;
+    configs.boss.bosses = vec![boss(
+        Milestone::Wave(2),
+        10,
+        &[(1., Pattern::Ring { count: 8 })],
+    )];
+    let mut game = TestApp::with_configs(configs);
+    game.run(60);
+    assert_eq!(game.app.world.resource::<BossSchedule>().spawned, 4);
+    assert_eq!(game.count::<CBoss>(), 1);
+    assert_eq!(game.count::<TEnemy>(), 4);
+}
+
+#[test]
+fn boss_phases_change_pattern_with_its_health() {
+    let mut configs = Configs::default();
+    configs.enemy.telegraph_time = 0.;
+    configs.boss.bosses = vec![boss(
+        Milestone::Score(0),
+        10,
+        &[
+            (1., Pattern::Ring { count: 8 }),
+            (
+                0.5,
+                Pattern::Aimed {
+                    count: 3,
+                    spread: 30.,
+                },
+            ),
+        ],
+    )];
+    let mut game = TestApp::with_configs(configs);
+    game.run(2);
+    assert_eq!(projectiles(&mut game), 8);
+
+    let boss = boss_entity(&mut game).unwrap();
+    game.app.world.get_mut::<CHealth>(boss).unwrap().current = 5;
+    game.run(60);
+    assert_eq!(game.app.world.get::<CBoss>(boss).unwrap().phase, 1);
+    assert_eq!(projectiles(&mut game), 11);
+}
+
+#[test]
+fn boss_takes_a_bullet_per_point_of_health_and_bursts_into_rings() {
+    let mut configs = Configs::default();
+    configs.enemy.telegraph_time = 0.;
+    let mut boss_type = boss(Milestone::Score(0), 3, &[(1., Pattern::Ring { count: 0 })]);
+    boss_type.phases[0].fire_rate = 0.;
+    configs.boss.bosses = vec![boss_type];
+    let mut game = TestApp::with_configs(configs);
+    game.step();
+    let boss = boss_entity(&mut game).unwrap();
+    let pos = game.pos(boss);
+    let target = Vec2::new(pos.x, 720. - pos.y);
+
+    for _ in 0..3 {
+        game.step_with(click(target));
+        game.run(10);
+    }
+    game.run(50);
+    assert_eq!(game.count::<CBoss>(), 0);
+    assert_eq!(game.score(), 5000);
+    let fragments = game
+        .app
+        .world
+        .query_filtered::<(), With<TFragment>>()
+        .iter(&game.app.world)
+        .count();
+    assert_eq!(fragments, 12);
+}
+
+#[test]
+fn piercing_bullet_hits_a_boss_once_on_its_way_through() {
+    let mut configs = Configs::default();
+    configs.enemy.telegraph_time = 0.;
+    configs.bullet.types[0].pierce = 3;
+    let mut boss_type = boss(Milestone::Score(0), 10, &[(1., Pattern::Ring { count: 0 })]);
+    boss_type.phases[0].fire_rate = 0.;
+    configs.boss.bosses = vec![boss_type];
+    let mut game = TestApp::with_configs(configs);
+    game.step();
+    let boss = boss_entity(&mut game).unwrap();
+    // in line with the player, who is in the middle
+    let pos = Vec2::new(900., 360.);
+    game.app.world.get_mut::<CTransform>(boss).unwrap().pos = pos;
+
+    game.step_with(click(pos));
+    let bullet = game
+        .app
+        .world
+        .query_filtered::<Entity, With<TBullet>>()
+        .single(&game.app.world);
+    // until it comes out the other side, several ticks after flying in
+    let mut inside = 0;
+    for _ in 0..60 {
+        game.step();
+        if game.pos(bullet).distance(pos) < 64. {
+            inside += 1;
+        } else if inside > 0 {
+            break;
+        }
+    }
+    assert!(inside > 3);
+    assert_eq!(game.app.world.get::<CHealth>(boss).unwrap().current, 9);
+    assert_eq!(game.app.world.get::<CPierce>(bullet).unwrap().0, 2);
+}
+
+#[test]
+fn boss_projectiles_cost_a_life_and_the_boss_carries_on() {
+    let mut configs = Configs::default();
+    configs.enemy.telegraph_time = 0.;
+    let mut boss_type = boss(
+        Milestone::Score(0),
+        10,
+        &[(
+            1.,
+            Pattern::Aimed {
+                count: 1,
+                spread: 0.,
+            },
+        )],
+    );
+    // one shot, from across the arena
+    boss_type.phases[0].projectile_speed = 400.;
+    boss_type.phases[0].fire_rate = 0.2;
+    configs.boss.bosses = vec![boss_type];
+    let mut game = TestApp::with_configs(configs);
+    game.run(150);
+    assert_eq!(game.lives_of(0), 2);
+    assert_eq!(game.count::<CBoss>(), 1);
+}
+
+/// Projectiles in flight that aren't part of a boss.
+fn projectiles(game: &mut TestApp) -> usize {
+    game.app
+        .world
+        .query_filtered::<(), (With<THostile>, Without<CBoss>)>()
+        .iter(&game.app.world)
+        .count()
+}
+
+#[test]
+fn enemy_projectiles_cost_a_life_and_pass_through_other_enemies() {
+    let mut configs = Configs::default();
+    configs.enemy.fire_rate = 0.5;
+    configs.enemy.accuracy = 1.;
+    configs.enemy.projectile_speed = 400.;
+    let mut game = TestApp::with_configs(configs);
+    let shooter = game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 6);
+    game.app.world.entity_mut(shooter).insert(CCooldown(0.));
+    // in the line of fire, but it never shoots
+    let bystander = game.spawn_enemy(Vec2::new(820., 360.), Vec2::ZERO, 6);
+
+    game.step();
+    assert_eq!(projectiles(&mut game), 1);
+    game.run(80);
+    assert_eq!(game.lives_of(0), 2);
+    assert!(game.app.world.get_entity(bystander).is_some());
+    assert_eq!(game.count::<TEnemy>(), 2);
+}
+
+#[test]
+fn difficulty_scales_how_often_enemies_fire() {
+    let fired = |difficulty| {
+        let mut configs = Configs::default();
+        configs.enemy.fire_rate = 1.;
+        configs.enemy.projectile_speed = 0.;
+        configs.enemy.difficulty = difficulty;
+        let mut game = TestApp::with_configs(configs);
+        let enemy = game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 6);
+        game.app.world.entity_mut(enemy).insert(CCooldown(0.));
+        game.run(100);
+        projectiles(&mut game)
+    };
+    assert_eq!(fired(Difficulty::Easy), 1);
+    assert_eq!(fired(Difficulty::Normal), 2);
+    assert_eq!(fired(Difficulty::Hard), 3);
+}
+
+#[test]
+fn settings_rewrite_only_the_values_that_changed() {
+    let mut text = "WindowConfig(
+    size: (1280.,720.),
+    // frames per second
+    frame_limit: 60.,
+    fullscreen: false,
+    // not read by the game
+    vsync: true,
+)"
+    .to_string();
+    set_field(&mut text, &[RonKey::Field("size")], &(1600f32, 900f32)).unwrap();
+    set_field(&mut text, &[RonKey::Field("frame_limit")], &60f32).unwrap();
+    set_field(&mut text, &[RonKey::Field("fullscreen")], &true).unwrap();
+    assert_eq!(
+        text,
+        "WindowConfig(
+    size: (1600.0,900.0),
+    // frames per second
+    frame_limit: 60.,
+    fullscreen: true,
+    // not read by the game
+    vsync: true,
+)"
+    );
+}
+
+#[test]
+fn settings_rebind_keys_inside_slots_and_add_missing_fields() {
+    let mut text = "(
+    slots: [
+        (controls: Gamepad(0)),
+        // arrows
+        (controls: Keyboard(up: Up, shoot: ControlRight)),
+    ]
+)"
+    .to_string();
+    let shoot = [
+        RonKey::Field("slots"),
+        RonKey::Index(1),
+        RonKey::Field("controls"),
+        RonKey::Field("shoot"),
+    ];
+    set_field(&mut text, &shoot, &KeyCode::Space).unwrap();
+    set_field(&mut text, &[RonKey::Field("count")], &2).unwrap();
+    assert_eq!(
+        text,
+        "(
+    slots: [
+        (controls: Gamepad(0)),
+        // arrows
+        (controls: Keyboard(up: Up, shoot: Space)),
+    ], count: 2
+)"
+    );
+    let missing = [
+        RonKey::Field("slots"),
+        RonKey::Index(2),
+        RonKey::Field("controls"),
+    ];
+    assert!(set_field(&mut text, &missing, &0).is_err());
+}
+
+#[test]
+fn telemetry_writes_a_row_per_frame_with_entity_and_collision_counts() {
+    let file = std::env::temp_dir()
+        .join(format!("comp4300-a2-telemetry-{}.csv", std::process::id()))
+        .to_string_lossy()
+        .into_owned();
+    let configs = Configs {
+        telemetry: Some(TelemetryConfig {
+            enabled: true,
+            format: TelemetryFormat::Csv,
+            file: file.clone(),
+        }),
+        ..Default::default()
+    };
+    let mut game = TestApp::with_configs(configs);
+    for x in [300., 700., 1100.] {
+        game.spawn_enemy(Vec2::new(x, 100.), Vec2::ZERO, 5);
+    }
+    game.run(10);
+    // flushed when the writer is dropped
+    drop(game);
+
+    let text = std::fs::read_to_string(&file).unwrap();
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    let column = |row: &Vec<&str>, name: &str| {
+        let i = header.iter().position(|column| *column == name).unwrap();
+        row[i].to_string()
+    };
+    // one row for the frame entering the game, then one per step
+    assert_eq!(rows.len(), 11);
+    assert_eq!(column(&rows[0], "state"), "InGame");
+    assert_eq!(column(&rows[1], "spawned"), "3");
+    for row in rows[1..].iter() {
+        assert_eq!(column(row, "players"), "1");
+        assert_eq!(column(row, "enemies"), "3");
+        // every enemy against the one player, once a tick
+        assert_eq!(column(row, "collisions_checked"), "3");
+        assert_eq!(column(row, "collisions_resolved"), "0");
+    }
+}
+
+fn hold(keys: &[KeyCode]) -> impl FnMut(usize) -> FrameInput + '_ {
+    move |_| FrameInput {
+        keys: keys.to_vec(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn focus_slows_the_world_down_while_the_meter_lasts() {
+    let mut configs = Configs::default();
+    configs.focus.scale = 0.5;
+    configs.focus.transition = 0.;
+    configs.focus.duration = 1.;
+    configs.focus.recharge = 2.;
+    let mut game = TestApp::with_configs(configs);
+    let enemy = game.spawn_enemy(Vec2::new(200., 100.), Vec2::new(60., 0.), 5);
+
+    // half a second at half speed
+    game.run_script(30, hold(&[KeyCode::ShiftLeft]));
+    assert!((game.pos(enemy).x - 215.).abs() < 0.01);
+    let focus = game.app.world.resource::<Focus>();
+    assert!(focus.active);
+    assert!((focus.meter - 0.5).abs() < 0.01);
+
+    game.run(30);
+    assert!((game.pos(enemy).x - 245.).abs() < 0.01);
+    let focus = game.app.world.resource::<Focus>();
+    assert!(!focus.active);
+    assert!((focus.meter - 0.75).abs() < 0.01);
+
+    // once the meter runs dry focus stops, and waits for some of it to come back
+    game.run_script(60, hold(&[KeyCode::ShiftLeft]));
+    let focus = game.app.world.resource::<Focus>();
+    assert!(!focus.active);
+    assert!(focus.meter < 0.25);
+    assert_eq!(game.app.world.resource::<TimeScale>().current, 1.);
+}
+
+#[test]
+fn focus_needs_some_meter_to_start_and_eases_in() {
+    let mut configs = Configs::default();
+    configs.focus.scale = 0.5;
+    configs.focus.transition = 0.5;
+    let mut game = TestApp::with_configs(configs);
+    game.app.world.resource_mut::<Focus>().meter = 0.1;
+    game.run_script(10, hold(&[KeyCode::ShiftLeft]));
+    assert!(!game.app.world.resource::<Focus>().active);
+    assert_eq!(game.app.world.resource::<TimeScale>().current, 1.);
+
+    game.app.world.resource_mut::<Focus>().meter = 1.;
+    game.run_script(15, hold(&[KeyCode::ShiftLeft]));
+    let scale = game.app.world.resource::<TimeScale>();
+    assert_eq!(scale.target, 0.5);
+    assert!((scale.current - 0.75).abs() < 0.01);
+    game.run_script(15, hold(&[KeyCode::ShiftLeft]));
+    assert!((game.app.world.resource::<TimeScale>().current - 0.5).abs() < 0.01);
+}
+
+#[test]
+fn time_scale_slows_enemy_spawns() {
+    let mut configs = Configs::default();
+    configs.enemy.spawn_interval = 1.;
+    configs.focus.transition = 0.;
+    let mut game = TestApp::with_configs(configs);
+    game.app.world.resource_mut::<TimeScale>().base = 0.5;
+    // a second at normal speed is only half of one
+    game.run(90);
+    assert_eq!(game.count::<TEnemy>(), 0);
+    game.run(40);
+    assert_eq!(game.count::<TEnemy>(), 1);
+}
+
+fn head_on(crowding: Crowding) -> (TestApp, Entity, Entity) {
+    let mut configs = Configs::default();
+    configs.enemy.crowding = crowding;
+    let mut game = TestApp::with_configs(configs);
+    let heavy = game.spawn_enemy(Vec2::new(400., 150.), Vec2::new(100., 0.), 8);
+    let light = game.spawn_enemy(Vec2::new(600., 150.), Vec2::new(-100., 0.), 3);
+    game.run(60);
+    (game, heavy, light)
+}
+
+#[test]
+fn crowded_enemies_bounce_off_each_other_by_vertex_count() {
+    let (game, heavy, light) = head_on(Crowding::Bounce);
+    let [heavy, light] =
+        [heavy, light].map(|e| game.app.world.get::<CTransform>(e).unwrap().clone());
+    assert!(light.pos.x - heavy.pos.x >= 63.9);
+    // 8 against 3 vertices: the light one is thrown back, the heavy one barely stops
+    assert!((heavy.vel.x + 9.09).abs() < 0.1, "{}", heavy.vel.x);
+    assert!((light.vel.x - 190.9).abs() < 0.1, "{}", light.vel.x);
+    assert!((8. * heavy.vel.x + 3. * light.vel.x - 500.).abs() < 0.1);
+}
+
+#[test]
+fn enemies_overlap_unless_crowding_is_on() {
+    let (game, heavy, light) = head_on(Crowding::Overlap);
+    assert!(game.pos(heavy).x > game.pos(light).x);
+}
+
+fn fragment_gap(game: &mut TestApp) -> f32 {
+    let positions: Vec<Vec2> = game
+        .app
+        .world
+        .query_filtered::<&CTransform, With<TFragment>>()
+        .iter(&game.app.world)
+        .map(|tf| tf.pos)
+        .collect();
+    assert_eq!(positions.len(), 6);
+    let mut gap = f32::MAX;
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            gap = gap.min(a.distance(*b));
+        }
+    }
+    gap
+}
+
+fn shatter(crowding: Crowding) -> TestApp {
+    let mut configs = Configs::default();
+    configs.enemy.crowding = crowding;
+    let mut game = TestApp::with_configs(configs);
+    game.spawn_enemy(Vec2::new(1000., 360.), Vec2::ZERO, 6);
+    game.step_with(click(Vec2::new(1000., 360.)));
+    while game.count::<TEnemy>() > 0 {
+        game.step();
+    }
+    game.run(20);
+    game
+}
+
+#[test]
+fn crowded_fragments_push_apart_instead_of_stacking() {
+    // fragments are half the enemy's radius, so they stop touching 32 apart
+    assert!(fragment_gap(&mut shatter(Crowding::Overlap)) < 24.);
+    assert!(fragment_gap(&mut shatter(Crowding::Bounce)) > 24.);
+}
+
+fn replay_configs(mode: ReplayMode, file: &str) -> Configs {
+    let mut configs = Configs::default();
+    configs.enemy.spawn_interval = 0.4;
+    configs.seed = 7;
+    configs.replay = Some(ReplayConfig {
+        mode,
+        file: file.to_string(),
+        speed: 1.,
+        seek_frames: 60,
+    });
+    configs
+}
+
+/// Everything a replay has to reproduce: where everything is, the score and the RNG state.
+fn replay_state(game: &mut TestApp) -> (Vec<Vec2>, u32, String) {
+    let mut positions: Vec<Vec2> = game
+        .app
+        .world
+        .query_filtered::<&CTransform, Without<TInactive>>()
+        .iter(&game.app.world)
+        .map(|tf| tf.pos)
+        .collect();
+    positions.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    let rng = ron::to_string(game.app.world.resource::<GlobalEntropy<WyRand>>()).unwrap();
+    (positions, game.score(), rng)
+}
+
+#[test]
+fn a_recorded_run_plays_back_exactly_even_after_seeking_back() {
+    let file = std::env::temp_dir()
+        .join(format!("comp4300-a2-replay-{}.ron", std::process::id()))
+        .to_string_lossy()
+        .into_owned();
+    let mut game = TestApp::with_configs(replay_configs(ReplayMode::Record, &file));
+    game.run_script(300, |frame| FrameInput {
+        keys: vec![if frame % 80 < 40 {
+            KeyCode::D
+        } else {
+            KeyCode::A
+        }],
+        click: (frame % 10 < 5)
+            .then(|| Vec2::new((frame * 37 % 1280) as f32, (frame * 53 % 720) as f32)),
+    });
+    // the replay is written on exit, and that frame is recorded too
+    game.app.world.send_event(AppExit);
+    game.step();
+    let recorded = replay_state(&mut game);
+    assert!(recorded.0.len() > 2);
+    let ticks = read_replay(&file).unwrap().frames.len();
+
+    // the update entering the game plays the first tick
+    let mut game = TestApp::with_configs(replay_configs(ReplayMode::Playback, &file));
+    game.run(ticks - 1);
+    assert_eq!(replay_state(&mut game), recorded);
+
+    // seeking back restarts the run, after the frame pressing it has played its own tick
+    let mut game = TestApp::with_configs(replay_configs(ReplayMode::Playback, &file));
+    game.run(200);
+    game.step_with(FrameInput {
+        keys: vec![KeyCode::Left],
+        ..Default::default()
+    });
+    game.run(ticks - 1 - 201 + 60);
+    assert_eq!(replay_state(&mut game), recorded);
+    std::fs::remove_file(&file).unwrap();
+}
+
+fn square(pos: Vec2, angle: f32) -> (CTransform, CCollision, CShape) {
+    (
+        CTransform::new(pos, Vec2::ZERO, angle),
+        CCollision {
+            rad: 20.,
+            mode: CollisionMode::Polygon,
+        },
+        CShape {
+            radius: 20.,
+            color: Color::WHITE,
+            outline: Color::WHITE,
+            thickness: 2.,
+            vertices: 4,
+        },
+    )
+}
+
+#[test]
+fn polygons_only_collide_where_their_corners_reach() {
+    // 35 apart the circles around them overlap, turned a quarter the squares' sides don't
+    let collide = |angle: f32| {
+        let (tf1, c1, sh1) = square(Vec2::ZERO, angle);
+        let (tf2, c2, sh2) = square(Vec2::new(35., 0.), angle);
+        is_collision(&tf1, &c1, &sh1, &tf2, &c2, &sh2)
+    };
+    assert!(!collide(std::f32::consts::FRAC_PI_4));
+    // unturned they point their corners at each other
+    assert!(collide(0.));
+}
+
+#[test]
+fn polygons_touching_along_an_edge_do_not_overlap() {
+    let a = [
+        Vec2::new(0., 0.),
+        Vec2::new(10., 0.),
+        Vec2::new(10., 10.),
+        Vec2::new(0., 10.),
+    ];
+    let b = |x: f32| a.map(|p| p + Vec2::new(x, 5.));
+    assert!(!polygons_overlap(&a, &b(10.)));
+    assert!(polygons_overlap(&a, &b(9.9)));
+}
+
+#[test]
+fn circles_can_pass_a_polygon_corner_without_touching_it() {
+    let (tf, _, mut shape) = square(Vec2::ZERO, std::f32::consts::FRAC_PI_4);
+    shape.radius = 30.;
+    let poly = polygon(&tf, &shape);
+    // beside a side
+    assert!(polygon_circle_overlap(&poly, Vec2::new(30., 0.), 9.));
+    assert!(!polygon_circle_overlap(&poly, Vec2::new(31., 0.), 9.));
+    // off a corner, within reach of both sides' lines, only the corner's axis tells them apart
+    assert!(!polygon_circle_overlap(&poly, Vec2::new(28., 28.), 9.));
+    assert!(polygon_circle_overlap(&poly, Vec2::new(28., 28.), 10.));
+}
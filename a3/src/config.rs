@@ -3,6 +3,10 @@
 use bevy::prelude::*;
 use bevy::reflect::TypePath;
 use bevy_common_assets::ron::RonAssetPlugin;
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use comp4300_common::cli::Cli;
+use comp4300_common::rng::{resolve_seed, RngSeed};
+use comp4300_common::telemetry::TelemetryFormat;
 
 use crate::AppState;
 
@@ -13,6 +17,8 @@ use crate::AppState;
 #[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
 pub struct WindowConfig {
     pub size: (f32, f32),
+    /// world units always on screen, letterboxed to fit the window
+    pub virtual_size: (f32, f32),
     pub frame_limit: f32,
     pub fullscreen: bool,
 }
@@ -24,6 +30,8 @@ struct WindowConfigHandle(Handle<WindowConfig>);
 pub struct FontConfig {
     pub file: String,
     pub size: f32,
+    // the debug text is drawn in its own color
+    #[allow(dead_code)]
     pub color: (f32, f32, f32),
 }
 
@@ -35,9 +43,14 @@ pub struct PlayerConfig {
     pub starting_position: (f32, f32),
     pub bounding_box: (f32, f32),
     pub walk_speed: f32,
+    // jumping, falling and the player's color come with the rest of the assignment
+    #[allow(dead_code)]
     pub jump_speed: f32,
+    #[allow(dead_code)]
     pub max_speed: f32,
+    #[allow(dead_code)]
     pub gravity: f32,
+    #[allow(dead_code)]
     pub color: (f32, f32, f32),
 }
 
@@ -60,6 +73,36 @@ pub struct LevelConfig {
 #[derive(Resource, Debug)]
 struct LevelConfigHandle(Handle<LevelConfig>);
 
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct RngConfig {
+    pub seed: Option<u64>,
+}
+
+#[derive(Resource, Debug)]
+struct RngConfigHandle(Handle<RngConfig>);
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct SaveConfig {
+    /// directory the save slot files are written to
+    pub dir: String,
+    pub slots: usize,
+}
+
+#[derive(Resource, Debug)]
+struct SaveConfigHandle(Handle<SaveConfig>);
+
+#[derive(serde::Deserialize, Asset, TypePath, Resource, Debug)]
+pub struct TelemetryConfig {
+    /// write a row for every frame while in game
+    pub enabled: bool,
+    pub format: TelemetryFormat,
+    /// file the rows are written to, replaced every session
+    pub file: String,
+}
+
+#[derive(Resource, Debug)]
+struct TelemetryConfigHandle(Handle<TelemetryConfig>);
+
 // endregion
 
 pub struct ConfigPlugin;
@@ -72,11 +115,19 @@ impl Plugin for ConfigPlugin {
             RonAssetPlugin::<PlayerConfig>::new(&["player.ron"]),
             RonAssetPlugin::<AnimationConfig>::new(&["animation.ron"]),
             RonAssetPlugin::<LevelConfig>::new(&["level.ron"]),
+            RonAssetPlugin::<RngConfig>::new(&["rng.ron"]),
+            RonAssetPlugin::<SaveConfig>::new(&["save.ron"]),
+            RonAssetPlugin::<TelemetryConfig>::new(&["telemetry.ron"]),
         ))
+        .init_resource::<Cli>()
         .add_systems(Startup, setup_config_handles)
         .add_systems(
             OnEnter(AppState::Loading),
-            load_config_handles_into_resources,
+            (
+                load_config_handles_into_resources,
+                load_more_config_handles_into_resources,
+                seed_rng,
+            ),
         )
         .add_systems(
             Update,
@@ -91,25 +142,45 @@ impl Plugin for ConfigPlugin {
     }
 }
 
-fn setup_config_handles(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_config_handles(mut commands: Commands, asset_server: Res<AssetServer>, cli: Res<Cli>) {
     info!("Setup plugin running");
-    let window_config = WindowConfigHandle(asset_server.load("config/config.window.ron"));
+    let window_config = WindowConfigHandle(asset_server.load(cli.config_path("config.window.ron")));
     commands.insert_resource(window_config);
 
-    let font_config_handle = FontConfigHandle(asset_server.load("config/config.font.ron"));
+    let font_config_handle =
+        FontConfigHandle(asset_server.load(cli.config_path("config.font.ron")));
     commands.insert_resource(font_config_handle);
 
-    let player_config_handle = PlayerConfigHandle(asset_server.load("config/config.player.ron"));
+    let player_config_handle =
+        PlayerConfigHandle(asset_server.load(cli.config_path("config.player.ron")));
     commands.insert_resource(player_config_handle);
 
     let animation_config_handle =
-        AnimationConfigHandle(asset_server.load("config/config.animation.ron"));
+        AnimationConfigHandle(asset_server.load(cli.config_path("config.animation.ron")));
     commands.insert_resource(animation_config_handle);
 
-    let level_config_handle = LevelConfigHandle(asset_server.load("config/1.level.ron"));
+    let level_config_handle = LevelConfigHandle(
+        asset_server.load(
+            cli.level
+                .clone()
+                .unwrap_or_else(|| cli.config_path("1.level.ron")),
+        ),
+    );
     commands.insert_resource(level_config_handle);
+
+    let rng_config_handle = RngConfigHandle(asset_server.load(cli.config_path("config.rng.ron")));
+    commands.insert_resource(rng_config_handle);
+
+    let save_config_handle =
+        SaveConfigHandle(asset_server.load(cli.config_path("config.save.ron")));
+    commands.insert_resource(save_config_handle);
+
+    let telemetry_config_handle =
+        TelemetryConfigHandle(asset_server.load(cli.config_path("config.telemetry.ron")));
+    commands.insert_resource(telemetry_config_handle);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn load_config_handles_into_resources(
     mut commands: Commands,
     mut state: ResMut<NextState<AppState>>,
@@ -123,8 +194,15 @@ fn load_config_handles_into_resources(
     mut animation_configs: ResMut<Assets<AnimationConfig>>,
     level_handle: Res<LevelConfigHandle>,
     mut level_configs: ResMut<Assets<LevelConfig>>,
+    save_handle: Res<SaveConfigHandle>,
+    mut save_configs: ResMut<Assets<SaveConfig>>,
+    cli: Res<Cli>,
 ) {
-    if let Some(r) = window_configs.remove(window_handle.0.id()) {
+    if let Some(mut r) = window_configs.remove(window_handle.0.id()) {
+        if let Some(size) = cli.window_size {
+            r.size = size;
+        }
+        r.fullscreen |= cli.fullscreen;
         commands.insert_resource(r);
     } else {
         error!("Failed to insert resource: WindowConfig");
@@ -154,9 +232,45 @@ fn load_config_handles_into_resources(
         error!("Failed to insert resource: LevelConfig");
     }
 
+    if let Some(r) = save_configs.remove(save_handle.0.id()) {
+        commands.insert_resource(r);
+    } else {
+        error!("Failed to insert resource: SaveConfig");
+    }
+
     state.set(AppState::PostLoading);
 }
 
+/// The configs that no longer fit in `load_config_handles_into_resources`' system parameters.
+fn load_more_config_handles_into_resources(
+    mut commands: Commands,
+    telemetry_handle: Res<TelemetryConfigHandle>,
+    mut telemetry_configs: ResMut<Assets<TelemetryConfig>>,
+) {
+    // only kept when enabled, the telemetry systems run while the resource exists
+    if let Some(r) = telemetry_configs.remove(telemetry_handle.0.id()) {
+        if r.enabled {
+            commands.insert_resource(r);
+        }
+    } else {
+        error!("Failed to insert resource: TelemetryConfig");
+    }
+}
+
+fn seed_rng(
+    mut commands: Commands,
+    rng_handle: Res<RngConfigHandle>,
+    mut rng_configs: ResMut<Assets<RngConfig>>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    cli: Res<Cli>,
+) {
+    let config_seed = rng_configs.remove(rng_handle.0.id()).and_then(|r| r.seed);
+    let seed = resolve_seed(cli.seed.or(config_seed));
+    rng.reseed(seed.to_le_bytes());
+    commands.insert_resource(RngSeed(seed));
+    info!("RNG seed: {}", seed);
+}
+
 fn wait_for_resources(mut state: ResMut<NextState<AppState>>) {
     state.set(AppState::InGame);
     info!("Setup plugin finished");
@@ -0,0 +1,188 @@
+// region: includes
+
+use bevy::{
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use leafwing_input_manager::prelude::*;
+
+use crate::config::{FontConfig, WindowConfig};
+use crate::{Action, AppState, CBoundingBox, CTransform, TDecoration, TPlayer, TTile, TimeScale};
+
+// endregion
+
+// time scales F2 steps through, to test the game slowed down or sped up
+const TIME_SCALES: [f32; 5] = [1., 0.5, 0.25, 0.1, 2.];
+
+// region: Debug Structs
+
+/// Which debug layers are drawn, toggled with T (textures), C (bounding boxes and velocities)
+/// and G (64px grid and the stats panel). F2 steps through time scales.
+#[derive(Resource)]
+pub struct DebugOverlay {
+    pub textures: bool,
+    pub collision: bool,
+    pub grid: bool,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        // tiles have no textures yet, so their bounding boxes are all that shows them
+        Self {
+            textures: true,
+            collision: true,
+            grid: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct TDebugText;
+
+// endregion
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FrameTimeDiagnosticsPlugin)
+            .init_resource::<DebugOverlay>()
+            .add_systems(OnEnter(AppState::InGame), s_setup_debug_text)
+            .add_systems(
+                Update,
+                (s_debug_toggles, s_debug_draw, s_debug_text)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+// region: systems
+
+fn s_setup_debug_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    font_config: Res<FontConfig>,
+    window_config: Res<WindowConfig>,
+) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load(font_config.file.clone()),
+                    font_size: font_config.size / 2.,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_alignment(TextAlignment::Left),
+            text_anchor: bevy::sprite::Anchor::TopLeft,
+            // the virtual resolution is fixed, so the top left corner never moves
+            transform: Transform::from_xyz(8., window_config.virtual_size.1 - 8., 10.),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        TDebugText,
+    ));
+}
+
+fn s_debug_toggles(
+    input_query: Query<&ActionState<Action>>,
+    mut overlay: ResMut<DebugOverlay>,
+    mut sprites: Query<&mut Visibility, With<TextureAtlasSprite>>,
+    mut scale: ResMut<TimeScale>,
+) {
+    let Ok(actions) = input_query.get_single() else {
+        return;
+    };
+    if actions.just_pressed(Action::ToggleTextures) {
+        overlay.textures = !overlay.textures;
+        for mut visibility in sprites.iter_mut() {
+            *visibility = if overlay.textures {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+    if actions.just_pressed(Action::ToggleCollision) {
+        overlay.collision = !overlay.collision;
+    }
+    if actions.just_pressed(Action::ToggleGrid) {
+        overlay.grid = !overlay.grid;
+    }
+    if actions.just_pressed(Action::CycleTimeScale) {
+        let next = TIME_SCALES
+            .iter()
+            .position(|s| *s == scale.target)
+            .map_or(0, |i| (i + 1) % TIME_SCALES.len());
+        scale.target = TIME_SCALES[next];
+        info!("Time scale: {}", scale.target);
+    }
+}
+
+fn s_debug_draw(
+    overlay: Res<DebugOverlay>,
+    window_config: Res<WindowConfig>,
+    query: Query<&CTransform, With<CBoundingBox>>,
+    mut gizmos: Gizmos,
+) {
+    if overlay.grid {
+        let (width, height) = window_config.virtual_size;
+        let color = Color::rgba(1., 1., 1., 0.2);
+        for x in (0..=width as u32).step_by(64) {
+            let x = x as f32;
+            gizmos.line_2d(Vec2::new(x, 0.), Vec2::new(x, height), color);
+        }
+        for y in (0..=height as u32).step_by(64) {
+            let y = y as f32;
+            gizmos.line_2d(Vec2::new(0., y), Vec2::new(width, y), color);
+        }
+    }
+
+    if overlay.collision {
+        for tf in query.iter() {
+            // velocity is per frame, stretch it to where the entity is ten frames from now
+            if tf.vel != Vec2::ZERO {
+                gizmos.line_2d(tf.pos, tf.pos + tf.vel * 10., Color::CYAN);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_debug_text(
+    overlay: Res<DebugOverlay>,
+    diagnostics: Res<DiagnosticsStore>,
+    state: Res<State<AppState>>,
+    mut text: Query<(&mut Text, &mut Visibility), With<TDebugText>>,
+    tiles: Query<(), With<TTile>>,
+    decorations: Query<(), With<TDecoration>>,
+    players: Query<(), With<TPlayer>>,
+    entities: Query<()>,
+) {
+    let Ok((mut text, mut visibility)) = text.get_single_mut() else {
+        return;
+    };
+    if !overlay.grid {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Inherited;
+
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.);
+    text.sections[0].value = format!(
+        "FPS: {:.0}\nState: {:?}\nEntities: {}\nTPlayer: {}\nTTile: {}\nTDecoration: {}",
+        fps,
+        state.get(),
+        entities.iter().count(),
+        players.iter().count(),
+        tiles.iter().count(),
+        decorations.iter().count(),
+    );
+}
+
+// endregion
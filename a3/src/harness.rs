@@ -0,0 +1,166 @@
+//! Headless test harness: builds the game with [`MinimalPlugins`], no window or renderer,
+//! configs injected as resources and actions scripted per frame.
+
+// region: includes
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
+use comp4300_common::rng::RngSeed;
+use leafwing_input_manager::prelude::*;
+
+use crate::config::*;
+use crate::{
+    save::SavePlugin, telemetry::TelemetryPlugin, Action, AppState, CTransform, GamePlugin, TPlayer,
+};
+
+// endregion
+
+// region: Harness Structs
+
+/// Every config the gameplay systems read. The level defaults to a two tile high floor.
+pub struct Configs {
+    pub player: PlayerConfig,
+    pub animation: AnimationConfig,
+    pub level: LevelConfig,
+    pub save: SaveConfig,
+    /// telemetry stays off without one
+    pub telemetry: Option<TelemetryConfig>,
+    pub seed: u64,
+}
+
+impl Default for Configs {
+    fn default() -> Self {
+        Self {
+            player: PlayerConfig {
+                starting_position: (2., 2.),
+                bounding_box: (48., 32.),
+                walk_speed: 5.,
+                jump_speed: 5.,
+                max_speed: 5.,
+                gravity: -1.,
+                color: (1., 0., 0.),
+            },
+            animation: AnimationConfig {
+                blue_slime_idle: (
+                    "textures/blue_slime/Idle.png".to_string(),
+                    48.,
+                    32.,
+                    8,
+                    1,
+                    200,
+                ),
+            },
+            level: LevelConfig {
+                tiles: (0..20)
+                    .flat_map(|x| [(1, 0, x as f32, 0.), (1, 0, x as f32, 1.)])
+                    .collect(),
+            },
+            // out of the source tree, and apart for every test run
+            save: SaveConfig {
+                dir: std::env::temp_dir()
+                    .join(format!("comp4300-a3-saves-{}", std::process::id()))
+                    .to_string_lossy()
+                    .into_owned(),
+                slots: 3,
+            },
+            telemetry: None,
+            seed: 0,
+        }
+    }
+}
+
+pub struct TestApp {
+    pub app: App,
+    frame: usize,
+}
+
+// endregion
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::with_configs(Configs::default())
+    }
+
+    pub fn with_configs(configs: Configs) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            EntropyPlugin::<WyRand>::with_seed(configs.seed.to_le_bytes()),
+            GamePlugin,
+            SavePlugin,
+            TelemetryPlugin,
+        ))
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlas>()
+        .insert_resource(RngSeed(configs.seed))
+        .insert_resource(configs.player)
+        .insert_resource(configs.animation)
+        .insert_resource(configs.level)
+        .insert_resource(configs.save)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            std::time::Duration::from_secs_f64(1. / 60.),
+        ));
+        if let Some(telemetry) = configs.telemetry {
+            app.insert_resource(telemetry);
+        }
+
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+        // enter the game state, spawning the level and the player
+        app.update();
+
+        Self { app, frame: 0 }
+    }
+
+    /// Runs one frame with no action pressed.
+    pub fn step(&mut self) {
+        self.step_with(&[]);
+    }
+
+    pub fn step_with(&mut self, actions: &[Action]) {
+        let mut action_states = self.app.world.query::<&mut ActionState<Action>>();
+        for mut action_state in action_states.iter_mut(&mut self.app.world) {
+            for action in Action::variants() {
+                if actions.contains(&action) {
+                    action_state.press(action);
+                } else {
+                    action_state.release(action);
+                }
+            }
+        }
+        self.app.update();
+        self.frame += 1;
+    }
+
+    pub fn run(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Runs `frames` frames, asking `script` for the actions of each one by frame number.
+    pub fn run_script(&mut self, frames: usize, mut script: impl FnMut(usize) -> Vec<Action>) {
+        for _ in 0..frames {
+            let actions = script(self.frame);
+            self.step_with(&actions);
+        }
+    }
+
+    pub fn count<T: Component>(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<T>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    pub fn player_pos(&mut self) -> Vec2 {
+        self.app
+            .world
+            .query_filtered::<&CTransform, With<TPlayer>>()
+            .single(&self.app.world)
+            .pos
+    }
+}
@@ -2,16 +2,39 @@
 
 use std::time::Duration;
 
-use bevy::{prelude::*, window::*};
-use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand, resource::GlobalEntropy};
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{
+        camera::{ScalingMode, Viewport},
+        view::RenderLayers,
+    },
+    window::*,
+};
+use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
+use comp4300_common::cli::{Cli, Flags, MaxFramesPlugin};
+use comp4300_common::telemetry::CollisionCounts;
 use config::*;
+use debug::{DebugOverlay, DebugPlugin};
 use leafwing_input_manager::prelude::*;
-use rand_core::RngCore;
+use save::SavePlugin;
+use serde::{Deserialize, Serialize};
+use telemetry::TelemetryPlugin;
 
 mod config;
+mod debug;
+#[cfg(test)]
+mod harness;
+mod save;
+mod telemetry;
+#[cfg(test)]
+mod tests;
 
 // endregion
 
+// how much the time scale changes per second, so a new speed eases in instead of jumping
+const TIME_SCALE_RATE: f32 = 2.;
+
 // region: AppState
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
@@ -26,7 +49,7 @@ enum AppState {
 
 // region: Components
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CTransform {
     pos: Vec2,
     prev_pos: Vec2,
@@ -45,6 +68,8 @@ impl CTransform {
             angle: 0.,
         }
     }
+    // for the moving entities later in the assignment
+    #[allow(dead_code)]
     fn new_moving(pos: Vec2, vel: Vec2, angle: f32) -> Self {
         Self {
             pos,
@@ -56,7 +81,7 @@ impl CTransform {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CBoundingBox {
     size: Vec2,
     half_size: Vec2,
@@ -64,9 +89,11 @@ struct CBoundingBox {
 }
 
 impl CBoundingBox {
+    // an invisible box, for triggers later in the assignment
+    #[allow(dead_code)]
     pub fn new(size: Vec2) -> Self {
         Self {
-            size: size,
+            size,
             half_size: Vec2 {
                 x: size.x / 2.,
                 y: size.y / 2.,
@@ -76,23 +103,25 @@ impl CBoundingBox {
     }
     pub fn new_c(size: Vec2, color: Color) -> Self {
         Self {
-            size: size,
+            size,
             half_size: Vec2 {
                 x: size.x / 2.,
                 y: size.y / 2.,
             },
-            color: color,
+            color,
         }
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CLifespan {
     remaining: f32,
     total: f32,
 }
 
 impl CLifespan {
+    // for the short-lived entities later in the assignment
+    #[allow(dead_code)]
     fn new(total: f32) -> Self {
         Self {
             remaining: total,
@@ -101,10 +130,10 @@ impl CLifespan {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 struct CGravity(f32);
 
-#[derive(Component)]
+#[derive(Component, Clone, Serialize, Deserialize)]
 enum CState {
     Jumping,
     Standing,
@@ -133,7 +162,10 @@ impl CAnimated {
 #[derive(Clone)]
 struct Animations {
     idle: Animation,
+    // no walk or jump sheets yet
+    #[allow(dead_code)]
     walk: Option<Animation>,
+    #[allow(dead_code)]
     jump: Option<Animation>,
 }
 
@@ -153,6 +185,13 @@ enum Action {
     Jump,
     Shoot,
     Quit,
+    ToggleTextures,
+    ToggleCollision,
+    ToggleGrid,
+    CycleTimeScale,
+    QuickSave,
+    QuickLoad,
+    NextSaveSlot,
 }
 
 // endregion
@@ -168,6 +207,9 @@ struct TDecoration;
 #[derive(Component)]
 struct TPlayer;
 
+#[derive(Component)]
+struct TGameCamera;
+
 // endregion
 
 // region: Resources
@@ -177,6 +219,30 @@ struct GameAnimations {
     blue_slime: Animations,
 }
 
+/// How fast game time runs against real time. `current` eases towards `target`, player movement,
+/// lifespans and animation timers run on it.
+#[derive(Resource)]
+struct TimeScale {
+    current: f32,
+    target: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self {
+            current: 1.,
+            target: 1.,
+        }
+    }
+}
+
+impl TimeScale {
+    /// Game time passed this frame.
+    fn delta(&self, time: &Time) -> Duration {
+        time.delta().mul_f32(self.current.max(0.))
+    }
+}
+
 // endregion
 
 // region: Bundles
@@ -210,60 +276,87 @@ struct DecorationBundle {
 // endregion
 
 fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
-                primary_window: Some(Window {
-                    present_mode: PresentMode::AutoVsync,
-                    mode: WindowMode::Windowed,
-                    position: WindowPosition::Automatic,
-                    resolution: WindowResolution::new(800., 600.),
-                    title: "very cool game 2".to_string(),
-                    composite_alpha_mode: CompositeAlphaMode::Auto,
-                    resizable: false,
-                    enabled_buttons: EnabledButtons {
-                        minimize: true,
-                        maximize: false,
-                        close: true,
-                    },
-                    decorations: true,
-                    transparent: true,
-                    focused: true,
-                    window_level: WindowLevel::Normal,
-                    ..Default::default()
-                }),
-                ..Default::default()
-            }),
-            ConfigPlugin,
-            bevy_framepace::FramepacePlugin,
-            EntropyPlugin::<WyRand>::default(),
-            InputManagerPlugin::<Action>::default(),
-        ))
-        .add_state::<AppState>()
-        .add_systems(
-            OnEnter(AppState::InGame),
-            (
-                s_setup_window,
-                s_setup_input,
-                s_setup_level,
-                s_setup_textures,
-            ),
-        )
+    let cli = Cli::from_env(
+        "a3",
+        Flags {
+            config_dir: true,
+            level: true,
+            seed: true,
+        },
+    );
+    let mut app = App::new();
+    app.add_plugins((
+        cli.default_plugins(Window {
+            present_mode: PresentMode::AutoVsync,
+            mode: WindowMode::Windowed,
+            position: WindowPosition::Automatic,
+            resolution: WindowResolution::new(800., 600.),
+            title: "very cool game 2".to_string(),
+            composite_alpha_mode: CompositeAlphaMode::Auto,
+            resizable: true,
+            enabled_buttons: EnabledButtons {
+                minimize: true,
+                maximize: true,
+                close: true,
+            },
+            decorations: true,
+            transparent: true,
+            focused: true,
+            window_level: WindowLevel::Normal,
+            ..Default::default()
+        }),
+        ConfigPlugin,
+        EntropyPlugin::<WyRand>::default(),
+        InputManagerPlugin::<Action>::default(),
+        DebugPlugin,
+        SavePlugin,
+        TelemetryPlugin,
+        GamePlugin,
+        MaxFramesPlugin(cli.max_frames),
+    ));
+    if cli.headless {
+        // the schedule runner paces headless runs, the settings stay for the systems setting them
+        app.init_resource::<bevy_framepace::FramepaceSettings>();
+    } else {
+        app.add_plugins(bevy_framepace::FramepacePlugin);
+    }
+    app.insert_resource(cli)
+        .add_systems(OnEnter(AppState::InGame), s_setup_window)
         .add_systems(
             Update,
-            (
-                s_lifespan,
-                s_movement,
-                s_collision.after(s_movement),
-                s_match_anims_to_transforms.after(s_collision),
-                s_animation.after(s_collision),
-                s_render.after(s_collision),
-            )
-                .run_if(in_state(AppState::InGame)),
+            (s_render.after(s_collision), s_letterbox).run_if(in_state(AppState::InGame)),
         )
         .run();
 }
 
+/// The game's state and gameplay systems, without any window or rendering so it can also run
+/// headless.
+struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (s_setup_input, s_setup_level, s_setup_textures),
+            )
+            .add_systems(
+                Update,
+                (
+                    s_time_scale,
+                    s_lifespan.after(s_time_scale),
+                    s_movement.after(s_time_scale),
+                    s_collision.after(s_movement),
+                    s_match_anims_to_transforms.after(s_collision),
+                    s_animation.after(s_collision).after(s_time_scale),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .init_resource::<CollisionCounts>()
+            .init_resource::<TimeScale>();
+    }
+}
+
 // region: startup systems
 
 fn s_setup_textures(
@@ -310,15 +403,36 @@ fn s_setup_window(
     mut windows: Query<&mut Window>,
     window_config: Res<WindowConfig>,
 ) {
-    commands.spawn(Camera2dBundle {
-        projection: OrthographicProjection {
-            viewport_origin: Vec2 { x: 0., y: 0. },
-            near: -1000.,
-            far: 1000.,
+    // renders nothing, only clears the bars around the game camera's viewport
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: -1,
+                ..Default::default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+            },
             ..Default::default()
         },
-        ..Default::default()
-    });
+        RenderLayers::layer(RenderLayers::TOTAL_LAYERS as u8 - 1),
+    ));
+    commands.spawn((
+        Camera2dBundle {
+            projection: OrthographicProjection {
+                viewport_origin: Vec2 { x: 0., y: 0. },
+                near: -1000.,
+                far: 1000.,
+                scaling_mode: ScalingMode::Fixed {
+                    width: window_config.virtual_size.0,
+                    height: window_config.virtual_size.1,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        TGameCamera,
+    ));
 
     let mut window = windows.single_mut();
     window
@@ -346,12 +460,19 @@ fn s_setup_input(mut commands: Commands) {
             (KeyCode::D, Action::Right),
             (KeyCode::Space, Action::Shoot),
             (KeyCode::Escape, Action::Quit),
+            (KeyCode::T, Action::ToggleTextures),
+            (KeyCode::C, Action::ToggleCollision),
+            (KeyCode::G, Action::ToggleGrid),
+            (KeyCode::F2, Action::CycleTimeScale),
+            (KeyCode::F5, Action::QuickSave),
+            (KeyCode::F9, Action::QuickLoad),
+            (KeyCode::F6, Action::NextSaveSlot),
         ]),
     });
 }
 
 fn s_setup_level(mut commands: Commands, level_config: Res<LevelConfig>) {
-    for (solid, kind, grid_x, grid_y) in level_config.tiles.iter() {
+    for (solid, _kind, grid_x, grid_y) in level_config.tiles.iter() {
         let grid_x = (*grid_x * 64.) + 32.;
         let grid_y = (*grid_y * 64.) + 32.;
         if *solid == 1 {
@@ -374,7 +495,38 @@ fn s_setup_level(mut commands: Commands, level_config: Res<LevelConfig>) {
 
 // region: systems
 
-fn s_render(circle_query: Query<(&CBoundingBox, &CTransform)>, mut gizmos: Gizmos) {
+/// Fits the game camera's viewport to the largest area of the virtual aspect ratio that the
+/// window can hold, centered.
+fn s_letterbox(
+    windows: Query<&Window, Changed<Window>>,
+    mut cameras: Query<&mut Camera, With<TGameCamera>>,
+    window_config: Res<WindowConfig>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let virtual_size = Vec2::new(window_config.virtual_size.0, window_config.virtual_size.1);
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    let Some((physical_position, physical_size)) = letterbox(window_size, virtual_size) else {
+        return;
+    };
+    for mut camera in cameras.iter_mut() {
+        camera.viewport = Some(Viewport {
+            physical_position,
+            physical_size,
+            ..Default::default()
+        });
+    }
+}
+
+fn s_render(
+    circle_query: Query<(&CBoundingBox, &CTransform)>,
+    mut gizmos: Gizmos,
+    overlay: Res<DebugOverlay>,
+) {
+    if !overlay.collision {
+        return;
+    }
     for (bb, tf) in circle_query.iter() {
         gizmos.rect_2d(tf.pos, tf.angle, bb.size, bb.color)
     }
@@ -383,18 +535,14 @@ fn s_render(circle_query: Query<(&CBoundingBox, &CTransform)>, mut gizmos: Gizmo
 fn s_movement(
     input_query: Query<&ActionState<Action>>,
     mut player_query: Query<(&CBoundingBox, &mut CTransform, &TPlayer)>,
-    window: Query<&Window>,
     player_config: Res<PlayerConfig>,
+    scale: Res<TimeScale>,
     mut app_exit_events: ResMut<Events<bevy::app::AppExit>>,
 ) {
-    let window = window.single();
-    let width = window.resolution.width();
-    let height = window.resolution.height();
-
     let actions = input_query.single();
 
     match player_query.get_single_mut() {
-        Ok((bb, mut tf, _)) => {
+        Ok((_, mut tf, _)) => {
             tf.vel = Vec2::ZERO;
             if actions.pressed(Action::Up) {
                 tf.vel.y = 1.;
@@ -413,7 +561,7 @@ fn s_movement(
             }
 
             tf.vel = tf.vel.normalize_or_zero();
-            tf.vel = tf.vel * player_config.walk_speed;
+            tf.vel *= player_config.walk_speed * scale.current.max(0.);
 
             tf.prev_pos = tf.pos;
             tf.pos = tf.pos + tf.vel;
@@ -424,15 +572,17 @@ fn s_movement(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn s_collision(
     query: Query<(&CBoundingBox, &CTransform), (With<TTile>, Without<TPlayer>)>,
     mut player_query: Query<(&CBoundingBox, &mut CTransform), With<TPlayer>>,
+    mut collisions: ResMut<CollisionCounts>,
 ) {
     if let Ok((p_bb, mut p_tf)) = player_query.get_single_mut() {
         for (t_bb, t_tf) in query.iter() {
             // Calculating collisions using Axis Aligned Bounding Boxes
             let overlap = get_bounding_overlap(&p_bb.size, &p_tf.pos, &t_bb.size, &t_tf.pos);
-            if overlap.y > 0. && overlap.x > 0. {
+            if collisions.record(overlap.y > 0. && overlap.x > 0.) {
                 let prev_overlap =
                     get_bounding_overlap(&p_bb.size, &p_tf.prev_pos, &t_bb.size, &t_tf.prev_pos);
                 resolve_collision_by_moving(&overlap, &prev_overlap, &mut p_tf.pos, &t_tf.pos)
@@ -441,19 +591,33 @@ fn s_collision(
     };
 }
 
-fn s_lifespan(mut commands: Commands, mut query: Query<(Entity, &mut CLifespan)>, time: Res<Time>) {
+fn s_time_scale(mut scale: ResMut<TimeScale>, time: Res<Time>) {
+    let step = TIME_SCALE_RATE * time.delta_seconds();
+    scale.current += (scale.target - scale.current).clamp(-step, step);
+}
+
+fn s_lifespan(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut CLifespan)>,
+    time: Res<Time>,
+    scale: Res<TimeScale>,
+) {
     for (e, mut ls) in query.iter_mut() {
-        ls.remaining -= time.delta_seconds();
+        ls.remaining -= scale.delta(&time).as_secs_f32();
         if ls.remaining < 0. {
             commands.entity(e).despawn();
         }
     }
 }
 
-fn s_animation(mut query: Query<(&mut CAnimated, &mut TextureAtlasSprite)>, time: Res<Time>) {
+fn s_animation(
+    mut query: Query<(&mut CAnimated, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+    scale: Res<TimeScale>,
+) {
     for (mut anim, mut sprite) in query.iter_mut() {
         if let Some(timer) = anim.timer.as_mut() {
-            timer.tick(time.delta());
+            timer.tick(scale.delta(&time));
             if timer.just_finished() {
                 sprite.index = if sprite.index == anim.anims.idle.len - 1 {
                     0
@@ -461,7 +625,6 @@ fn s_animation(mut query: Query<(&mut CAnimated, &mut TextureAtlasSprite)>, time
                     sprite.index + 1
                 };
             }
-        } else {
         }
     }
 }
@@ -504,6 +667,22 @@ fn spawn_player(
     });
 }
 
+/// Position and size in physical pixels of the centered viewport with the aspect ratio of
+/// `virtual_size`, or None while the window has no area (minimized).
+fn letterbox(window_size: UVec2, virtual_size: Vec2) -> Option<(UVec2, UVec2)> {
+    if window_size.x == 0 || window_size.y == 0 {
+        return None;
+    }
+    let window = window_size.as_vec2();
+    let scale = (window / virtual_size).min_element();
+    let size = (virtual_size * scale)
+        .round()
+        .as_uvec2()
+        .min(window_size)
+        .max(UVec2::ONE);
+    Some(((window_size - size) / 2, size))
+}
+
 fn get_bounding_overlap(p_size: &Vec2, p_pos: &Vec2, t_size: &Vec2, t_pos: &Vec2) -> Vec2 {
     let diff = Vec2::new((p_pos.x - t_pos.x).abs(), (p_pos.y - t_pos.y).abs());
     let overlap_x = (p_size.x / 2.) + (t_size.x / 2.) - diff.x;
@@ -553,12 +732,4 @@ fn resolve_collision_by_moving(
     }
 }
 
-fn rng_range(rng: &mut GlobalEntropy<WyRand>, min: f32, max: f32) -> f32 {
-    min + (rng.next_u32() as f32) / ((u32::MAX as f32) / (max - min))
-}
-
-fn rng_range_u32(rng: &mut GlobalEntropy<WyRand>, min: u32, max: u32) -> u32 {
-    min + (rng.next_u32() % (1 + max - min))
-}
-
 // endregion
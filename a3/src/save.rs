@@ -0,0 +1,283 @@
+//! Quick save and load of the whole level to RON files, one per save slot. Loading restores
+//! the RNG too, so a loaded game carries on exactly as the saved one would have.
+
+// region: includes
+
+use bevy::prelude::*;
+use bevy_rand::{prelude::WyRand, resource::GlobalEntropy};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::SaveConfig;
+use crate::debug::DebugOverlay;
+use crate::{
+    s_animation, Action, AppState, CAnimated, CBoundingBox, CGravity, CLifespan, CState,
+    CTransform, GameAnimations, TDecoration, TPlayer, TTile,
+};
+
+// endregion
+
+// region: Save Structs
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+enum SavedTag {
+    Player,
+    Tile,
+    Decoration,
+}
+
+/// Where an animated entity is in its animation, the texture atlases come from
+/// [`GameAnimations`] again on load.
+#[derive(Serialize, Deserialize)]
+struct SavedAnimation {
+    timer: Option<Timer>,
+    frame: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntity {
+    tag: SavedTag,
+    transform: CTransform,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bounding_box: Option<CBoundingBox>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lifespan: Option<CLifespan>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gravity: Option<CGravity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    state: Option<CState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    animation: Option<SavedAnimation>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    rng: GlobalEntropy<WyRand>,
+    entities: Vec<SavedEntity>,
+}
+
+/// Saves the game to, or loads it from, a save slot at the end of the frame.
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SaveRequest {
+    Save(usize),
+    Load(usize),
+}
+
+/// The slot QuickSave and QuickLoad use.
+#[derive(Resource, Default, Debug)]
+struct SaveSlot(usize);
+
+type SavedQuery = (
+    &'static CTransform,
+    Option<&'static CBoundingBox>,
+    Option<&'static CLifespan>,
+    Option<&'static CGravity>,
+    Option<&'static CState>,
+    Option<(&'static CAnimated, &'static TextureAtlasSprite)>,
+    (Has<TPlayer>, Has<TTile>, Has<TDecoration>),
+);
+
+// endregion
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveRequest>()
+            .init_resource::<SaveSlot>()
+            .add_systems(
+                Update,
+                (s_save_keys, s_save, s_load)
+                    .chain()
+                    .after(s_animation)
+                    .run_if(resource_exists::<SaveConfig>())
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+// region: systems
+
+fn s_save_keys(
+    input_query: Query<&ActionState<Action>>,
+    mut slot: ResMut<SaveSlot>,
+    save_config: Res<SaveConfig>,
+    mut requests: EventWriter<SaveRequest>,
+) {
+    let Ok(actions) = input_query.get_single() else {
+        return;
+    };
+    if actions.just_pressed(Action::NextSaveSlot) {
+        slot.0 = (slot.0 + 1) % save_config.slots.max(1);
+        info!("Save slot {}", slot.0 + 1);
+    }
+    if actions.just_pressed(Action::QuickSave) {
+        requests.send(SaveRequest::Save(slot.0));
+    }
+    if actions.just_pressed(Action::QuickLoad) {
+        requests.send(SaveRequest::Load(slot.0));
+    }
+}
+
+fn s_save(
+    mut requests: EventReader<SaveRequest>,
+    save_config: Res<SaveConfig>,
+    rng: Res<GlobalEntropy<WyRand>>,
+    query: Query<SavedQuery>,
+) {
+    for request in requests.read() {
+        let SaveRequest::Save(slot) = *request else {
+            continue;
+        };
+        let file = SaveFile {
+            rng: rng.clone(),
+            entities: query.iter().filter_map(save_entity).collect(),
+        };
+        let path = slot_path(&save_config, slot);
+        match write_save(&path, &file) {
+            Ok(()) => info!("Saved {} entities to {}", file.entities.len(), path),
+            Err(err) => error!("save.rs::s_save: {}", err),
+        }
+    }
+}
+
+fn s_load(
+    mut commands: Commands,
+    mut requests: EventReader<SaveRequest>,
+    save_config: Res<SaveConfig>,
+    mut rng: ResMut<GlobalEntropy<WyRand>>,
+    game_animations: Option<Res<GameAnimations>>,
+    overlay: Option<Res<DebugOverlay>>,
+    entities: Query<Entity, With<CTransform>>,
+) {
+    // only the last load of the frame matters
+    let Some(slot) = requests
+        .read()
+        .filter_map(|request| match request {
+            SaveRequest::Load(slot) => Some(*slot),
+            SaveRequest::Save(_) => None,
+        })
+        .last()
+    else {
+        return;
+    };
+    let path = slot_path(&save_config, slot);
+    let file = match read_save(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            error!("save.rs::s_load: {}", err);
+            return;
+        }
+    };
+    let Some(game_animations) = game_animations else {
+        error!("save.rs::s_load: textures are not set up yet");
+        return;
+    };
+
+    for e in entities.iter() {
+        commands.entity(e).despawn_recursive();
+    }
+    *rng = file.rng;
+    let visibility = match overlay {
+        Some(overlay) if !overlay.textures => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    };
+    let count = file.entities.len();
+    for saved in file.entities {
+        load_entity(&mut commands, &game_animations, visibility, saved);
+    }
+    info!("Loaded {} entities from {}", count, path);
+}
+
+// endregion
+
+// region: functions
+
+fn save_entity(
+    (tf, bb, lifespan, gravity, state, anim, (player, tile, decoration)): bevy::ecs::query::QueryItem<
+        SavedQuery,
+    >,
+) -> Option<SavedEntity> {
+    let tag = match (player, tile, decoration) {
+        (true, _, _) => SavedTag::Player,
+        (_, true, _) => SavedTag::Tile,
+        (_, _, true) => SavedTag::Decoration,
+        _ => return None,
+    };
+    Some(SavedEntity {
+        tag,
+        transform: tf.clone(),
+        bounding_box: bb.cloned(),
+        lifespan: lifespan.cloned(),
+        gravity: gravity.cloned(),
+        state: state.cloned(),
+        animation: anim.map(|(anim, sprite)| SavedAnimation {
+            timer: anim.timer.clone(),
+            frame: sprite.index,
+        }),
+    })
+}
+
+fn load_entity(
+    commands: &mut Commands,
+    game_animations: &GameAnimations,
+    visibility: Visibility,
+    saved: SavedEntity,
+) {
+    let pos = saved.transform.pos;
+    let mut e = commands.spawn(saved.transform);
+    match saved.tag {
+        SavedTag::Player => e.insert(TPlayer),
+        SavedTag::Tile => e.insert(TTile),
+        SavedTag::Decoration => e.insert(TDecoration),
+    };
+    if let Some(c) = saved.bounding_box {
+        e.insert(c);
+    }
+    if let Some(c) = saved.lifespan {
+        e.insert(c);
+    }
+    if let Some(c) = saved.gravity {
+        e.insert(c);
+    }
+    if let Some(c) = saved.state {
+        e.insert(c);
+    }
+    // the blue slime is the only animation there is
+    if let Some(anim) = saved.animation {
+        let anims = game_animations.blue_slime.clone();
+        e.insert((
+            SpriteSheetBundle {
+                texture_atlas: anims.idle.anim.clone(),
+                sprite: TextureAtlasSprite::new(anim.frame),
+                transform: Transform::from_xyz(pos.x, pos.y, 0.),
+                visibility,
+                ..Default::default()
+            },
+            CAnimated {
+                anims,
+                timer: anim.timer,
+            },
+        ));
+    }
+}
+
+pub fn slot_path(save_config: &SaveConfig, slot: usize) -> String {
+    format!("{}/slot{}.save.ron", save_config.dir, slot + 1)
+}
+
+pub fn read_save(path: &str) -> Result<SaveFile, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    ron::from_str(&text).map_err(|err| format!("{}: {}", path, err))
+}
+
+pub fn write_save(path: &str, file: &SaveFile) -> Result<(), String> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    let text = ron::ser::to_string_pretty(file, ron::ser::PrettyConfig::default())
+        .map_err(|err| format!("{}: {}", path, err))?;
+    std::fs::write(path, text).map_err(|err| format!("{}: {}", path, err))
+}
+
+// endregion
@@ -0,0 +1,107 @@
+//! Opt-in per-frame telemetry from `TelemetryConfig`, for profiling the collision passes:
+//! frame time, entity counts by tag, collisions, spawns and despawns and state.
+
+// region: includes
+
+use bevy::{app::AppExit, ecs::entity::Entities, prelude::*};
+use comp4300_common::telemetry::{CollisionCounts, TelemetryWriter, Value};
+
+use crate::config::TelemetryConfig;
+use crate::{AppState, CTransform, TDecoration, TPlayer, TTile};
+
+// endregion
+
+// rows buffered between writes to disk
+const FLUSH_FRAMES: u64 = 60;
+
+// region: Telemetry Structs
+
+/// The open telemetry file, only there while telemetry is enabled and the file could be created.
+#[derive(Resource)]
+struct Telemetry {
+    writer: TelemetryWriter,
+    frame: u64,
+}
+
+// endregion
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            s_open_telemetry.run_if(resource_exists::<TelemetryConfig>()),
+        )
+        .add_systems(
+            Last,
+            s_telemetry
+                .run_if(resource_exists::<Telemetry>())
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+// region: systems
+
+fn s_open_telemetry(mut commands: Commands, telemetry_config: Res<TelemetryConfig>) {
+    match TelemetryWriter::create(&telemetry_config.file, telemetry_config.format) {
+        Ok(writer) => {
+            info!("Writing telemetry to {}", telemetry_config.file);
+            commands.insert_resource(Telemetry { writer, frame: 0 });
+        }
+        Err(err) => error!("telemetry.rs::s_open_telemetry: {}", err),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn s_telemetry(
+    mut commands: Commands,
+    mut telemetry: ResMut<Telemetry>,
+    mut collisions: ResMut<CollisionCounts>,
+    entities: &Entities,
+    tags: Query<(Has<TPlayer>, Has<TTile>, Has<TDecoration>)>,
+    spawned: Query<(), Added<CTransform>>,
+    mut despawned: RemovedComponents<CTransform>,
+    state: Res<State<AppState>>,
+    time: Res<Time<Real>>,
+    exit: EventReader<AppExit>,
+) {
+    let (mut players, mut tiles, mut decorations) = (0usize, 0usize, 0usize);
+    for (player, tile, decoration) in tags.iter() {
+        players += player as usize;
+        tiles += tile as usize;
+        decorations += decoration as usize;
+    }
+
+    let row: &[(&'static str, Value)] = &[
+        ("frame", telemetry.frame.into()),
+        ("frame_time_ms", (time.delta_seconds_f64() * 1000.).into()),
+        ("state", format!("{:?}", state.get()).into()),
+        ("entities", entities.len().into()),
+        ("players", players.into()),
+        ("tiles", tiles.into()),
+        ("decorations", decorations.into()),
+        ("collisions_checked", collisions.checked.into()),
+        ("collisions_resolved", collisions.resolved.into()),
+        ("spawned", spawned.iter().count().into()),
+        ("despawned", despawned.read().count().into()),
+    ];
+    *collisions = CollisionCounts::default();
+    telemetry.frame += 1;
+
+    let result = telemetry.writer.write_row(row).and_then(|()| {
+        if telemetry.frame.is_multiple_of(FLUSH_FRAMES) || !exit.is_empty() {
+            telemetry.writer.flush()
+        } else {
+            Ok(())
+        }
+    });
+    if let Err(err) = result {
+        // one error is enough, stop writing instead of repeating it every frame
+        error!("telemetry.rs::s_telemetry: {}", err);
+        commands.remove_resource::<Telemetry>();
+    }
+}
+
+// endregion
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use comp4300_common::telemetry::TelemetryFormat;
+
+use crate::config::TelemetryConfig;
+use crate::harness::{Configs, TestApp};
+use crate::{letterbox, Action, TDecoration, TPlayer, TTile, TimeScale};
+
+#[test]
+fn level_spawns_tiles_and_decorations() {
+    let mut configs = Configs::default();
+    configs.level.tiles.push((0, 0, 5., 5.));
+    let mut game = TestApp::with_configs(configs);
+    assert_eq!(game.count::<TTile>(), 40);
+    assert_eq!(game.count::<TDecoration>(), 1);
+    assert_eq!(game.count::<TPlayer>(), 1);
+}
+
+#[test]
+fn player_starts_standing_on_its_grid_cell() {
+    let mut game = TestApp::new();
+    assert_eq!(game.player_pos(), Vec2::new(2. * 64. + 32., 2. * 64. + 16.));
+}
+
+#[test]
+fn player_walks_right() {
+    let mut game = TestApp::new();
+    let start = game.player_pos();
+    game.run_script(10, |_| vec![Action::Right]);
+    assert_eq!(game.player_pos(), start + Vec2::new(50., 0.));
+}
+
+#[test]
+fn player_cannot_move_into_the_floor() {
+    let mut game = TestApp::new();
+    game.run_script(30, |_| vec![Action::Down]);
+    // the floor's top edge is two tiles up, the player's box is 32 high
+    assert!(game.player_pos().y >= 2. * 64. + 16. - 0.01);
+}
+
+#[test]
+fn quick_load_puts_the_player_back_where_it_was_saved() {
+    let mut game = TestApp::new();
+    game.run_script(5, |_| vec![Action::Right]);
+    game.step_with(&[Action::QuickSave]);
+    game.step();
+    let saved = game.player_pos();
+
+    game.run_script(10, |_| vec![Action::Right]);
+    assert_ne!(game.player_pos(), saved);
+    game.step_with(&[Action::QuickLoad]);
+    game.step();
+    assert_eq!(game.player_pos(), saved);
+    assert_eq!(game.count::<TPlayer>(), 1);
+    assert_eq!(game.count::<TTile>(), 40);
+}
+
+#[test]
+fn letterbox_centers_the_virtual_aspect_ratio() {
+    let virtual_size = Vec2::new(1280., 720.);
+    // wider than 16:9 gives bars on the sides, taller gives bars above and below
+    assert_eq!(
+        letterbox(UVec2::new(2000, 720), virtual_size),
+        Some((UVec2::new(360, 0), UVec2::new(1280, 720)))
+    );
+    assert_eq!(
+        letterbox(UVec2::new(640, 720), virtual_size),
+        Some((UVec2::new(0, 180), UVec2::new(640, 360)))
+    );
+    assert_eq!(letterbox(UVec2::new(0, 720), virtual_size), None);
+}
+
+#[test]
+fn telemetry_counts_tile_collisions_every_frame() {
+    let file = std::env::temp_dir()
+        .join(format!(
+            "comp4300-a3-telemetry-{}.ndjson",
+            std::process::id()
+        ))
+        .to_string_lossy()
+        .into_owned();
+    let configs = Configs {
+        telemetry: Some(TelemetryConfig {
+            enabled: true,
+            format: TelemetryFormat::Ndjson,
+            file: file.clone(),
+        }),
+        ..Default::default()
+    };
+    let mut game = TestApp::with_configs(configs);
+    game.run(5);
+    // flushed when the writer is dropped
+    drop(game);
+
+    let text = std::fs::read_to_string(&file).unwrap();
+    let rows: Vec<&str> = text.lines().collect();
+    // one row for the frame entering the game, then one per step
+    assert_eq!(rows.len(), 6);
+    for (frame, row) in rows.iter().enumerate() {
+        assert!(row.starts_with(&format!("{{\"frame\":{},", frame)));
+        assert!(row.contains("\"state\":\"InGame\""));
+        assert!(row.contains("\"players\":1,\"tiles\":40,"));
+    }
+    // the player stands on the floor, tested against every tile once a frame
+    assert!(rows[5].contains("\"collisions_checked\":40,"));
+}
+
+fn sprite_index(game: &mut TestApp) -> usize {
+    game.app
+        .world
+        .query_filtered::<&TextureAtlasSprite, With<TPlayer>>()
+        .single(&game.app.world)
+        .index
+}
+
+#[test]
+fn animations_run_on_the_time_scale() {
+    // 200ms a frame
+    let mut game = TestApp::new();
+    game.run(27);
+    assert_eq!(sprite_index(&mut game), 2);
+
+    let mut game = TestApp::new();
+    game.app.world.insert_resource(TimeScale {
+        current: 0.5,
+        target: 0.5,
+    });
+    game.run(27);
+    assert_eq!(sprite_index(&mut game), 1);
+}
+
+#[test]
+fn player_walks_on_the_time_scale() {
+    let mut game = TestApp::new();
+    game.app.world.insert_resource(TimeScale {
+        current: 0.5,
+        target: 0.5,
+    });
+    let start = game.player_pos();
+    game.run_script(10, |_| vec![Action::Right]);
+    assert_eq!(game.player_pos(), start + Vec2::new(25., 0.));
+}
+
+#[test]
+fn time_scale_eases_towards_its_target() {
+    let mut game = TestApp::new();
+    game.app.world.resource_mut::<TimeScale>().target = 0.5;
+    game.run(6);
+    assert!((game.app.world.resource::<TimeScale>().current - 0.8).abs() < 0.01);
+    game.run(30);
+    assert_eq!(game.app.world.resource::<TimeScale>().current, 0.5);
+}
@@ -0,0 +1,380 @@
+// region: includes
+
+use std::time::Duration;
+
+use bevy::app::{AppExit, PluginGroupBuilder, ScheduleRunnerPlugin};
+use bevy::log::{Level, LogPlugin};
+use bevy::prelude::*;
+use bevy::render::{settings::WgpuSettings, RenderPlugin};
+use bevy::window::{ExitCondition, WindowMode, WindowResolution};
+use bevy::winit::WinitPlugin;
+
+// endregion
+
+/// Where config files are loaded from when `--config-dir` isn't given, in the assets folder.
+pub const DEFAULT_CONFIG_DIR: &str = "config";
+
+// updates per second of a headless run, which has no window to pace it
+const HEADLESS_RATE: f64 = 60.;
+
+// region: Cli Structs
+
+/// The optional flags a binary takes, besides the ones every binary does.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Flags {
+    pub config_dir: bool,
+    pub level: bool,
+    pub seed: bool,
+}
+
+/// Command line arguments shared by the game binaries. Anything given here overrides the
+/// config files.
+#[derive(Resource, Clone, Default, PartialEq, Debug)]
+pub struct Cli {
+    /// directory the config files are read from, in the assets folder or absolute
+    pub config_dir: Option<String>,
+    /// level file, in the assets folder or absolute
+    pub level: Option<String>,
+    pub seed: Option<u64>,
+    pub window_size: Option<(f32, f32)>,
+    pub fullscreen: bool,
+    /// run without a window or renderer
+    pub headless: bool,
+    /// frames to run before exiting
+    pub max_frames: Option<u64>,
+    pub log_level: Option<Level>,
+}
+
+// endregion
+
+impl Cli {
+    /// Reads the process arguments. Prints the usage and exits on `--help`, or on a bad flag
+    /// together with what was wrong with it.
+    pub fn from_env(name: &str, flags: Flags) -> Self {
+        match Self::parse(flags, std::env::args().skip(1)) {
+            Ok(Some(cli)) => cli,
+            Ok(None) => {
+                println!("{}", usage(name, flags));
+                std::process::exit(0);
+            }
+            Err(err) => {
+                eprintln!("error: {}\n\n{}", err, usage(name, flags));
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// Parses `args`, without the program name. `None` when help was asked for.
+    pub fn parse(
+        flags: Flags,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Option<Self>, String> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("`{}` needs a value", flag))
+            };
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--config-dir" if flags.config_dir => cli.config_dir = Some(value()?),
+                "--level" if flags.level => cli.level = Some(value()?),
+                "--seed" if flags.seed => {
+                    let seed = value()?;
+                    cli.seed =
+                        Some(seed.parse().map_err(|_| {
+                            format!("`--seed` expects a whole number, got `{}`", seed)
+                        })?);
+                }
+                "--window-size" => cli.window_size = Some(parse_size(&value()?)?),
+                "--max-frames" => {
+                    let frames = value()?;
+                    cli.max_frames = Some(frames.parse().map_err(|_| {
+                        format!("`--max-frames` expects a whole number, got `{}`", frames)
+                    })?);
+                }
+                "--log-level" => {
+                    let level = value()?;
+                    cli.log_level = Some(level.parse().map_err(|_| {
+                        format!(
+                            "`--log-level` expects error, warn, info, debug or trace, got `{}`",
+                            level
+                        )
+                    })?);
+                }
+                "--fullscreen" | "--headless" if inline.is_some() => {
+                    return Err(format!("`{}` takes no value", flag));
+                }
+                "--fullscreen" => cli.fullscreen = true,
+                "--headless" => cli.headless = true,
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+        Ok(Some(cli))
+    }
+
+    /// Asset path of a config file in the config directory.
+    pub fn config_path(&self, file: &str) -> String {
+        let dir = self.config_dir.as_deref().unwrap_or(DEFAULT_CONFIG_DIR);
+        format!("{}/{}", dir.trim_end_matches('/'), file)
+    }
+
+    /// Bevy's default plugins around `window`, resized and logging as asked. Headless runs
+    /// keep the window entity for the systems that read its size, but never open it, and
+    /// skip the renderer.
+    pub fn default_plugins(&self, mut window: Window) -> PluginGroupBuilder {
+        if let Some((width, height)) = self.window_size {
+            window.resolution = WindowResolution::new(width, height);
+        }
+        if self.fullscreen {
+            window.mode = WindowMode::BorderlessFullscreen;
+        }
+        let mut plugins = DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(window),
+                exit_condition: if self.headless {
+                    ExitCondition::DontExit
+                } else {
+                    ExitCondition::OnAllClosed
+                },
+                ..Default::default()
+            })
+            .set(LogPlugin {
+                level: self.log_level.unwrap_or(Level::INFO),
+                ..Default::default()
+            });
+        if self.headless {
+            plugins = plugins
+                .disable::<WinitPlugin>()
+                .set(RenderPlugin {
+                    render_creation: WgpuSettings {
+                        backends: None,
+                        ..Default::default()
+                    }
+                    .into(),
+                })
+                .add(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                    1. / HEADLESS_RATE,
+                )));
+        }
+        plugins
+    }
+}
+
+/// Exits the app after running `max_frames` frames, when given.
+pub struct MaxFramesPlugin(pub Option<u64>);
+
+#[derive(Resource)]
+struct FramesLeft(u64);
+
+impl Plugin for MaxFramesPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(frames) = self.0 {
+            app.insert_resource(FramesLeft(frames))
+                .add_systems(Last, s_count_frames);
+        }
+    }
+}
+
+fn s_count_frames(mut frames: ResMut<FramesLeft>, mut exit: EventWriter<AppExit>) {
+    frames.0 = frames.0.saturating_sub(1);
+    if frames.0 == 0 {
+        exit.send(AppExit);
+    }
+}
+
+/// `WIDTHxHEIGHT`, in pixels.
+fn parse_size(text: &str) -> Result<(f32, f32), String> {
+    let error = || format!("`--window-size` expects WIDTHxHEIGHT, got `{}`", text);
+    let (width, height) = text.split_once(['x', 'X']).ok_or_else(error)?;
+    let width: f32 = width.trim().parse().map_err(|_| error())?;
+    let height: f32 = height.trim().parse().map_err(|_| error())?;
+    if width < 1. || height < 1. {
+        return Err(error());
+    }
+    Ok((width, height))
+}
+
+fn usage(name: &str, flags: Flags) -> String {
+    let mut lines = vec![
+        format!("Usage: {} [OPTIONS]", name),
+        String::new(),
+        "Options:".to_string(),
+    ];
+    if flags.config_dir {
+        lines.push(format!(
+            "      --config-dir <DIR>     config files directory, in assets/ or absolute [default: {}]",
+            DEFAULT_CONFIG_DIR
+        ));
+    }
+    if flags.level {
+        lines.push(
+            "      --level <FILE>         level file, in assets/ or absolute [default: 1.level.ron in the config directory]"
+                .to_string(),
+        );
+    }
+    if flags.seed {
+        lines
+            .push("      --seed <N>             seed the RNG, instead of the config's".to_string());
+    }
+    lines.extend(
+        [
+            "      --window-size <WxH>    window size in pixels, such as 1280x720",
+            "      --fullscreen           start in borderless fullscreen",
+            "      --headless             run without a window or renderer",
+            "      --max-frames <N>       exit after N frames",
+            "      --log-level <LEVEL>    error, warn, info, debug or trace [default: info]",
+            "  -h, --help                 print this help",
+        ]
+        .map(String::from),
+    );
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: Flags = Flags {
+        config_dir: true,
+        level: true,
+        seed: true,
+    };
+
+    fn parse(flags: Flags, args: &[&str]) -> Result<Option<Cli>, String> {
+        Cli::parse(flags, args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn help_asks_for_the_usage() {
+        assert_eq!(parse(ALL, &["--help"]), Ok(None));
+        assert_eq!(parse(ALL, &["--seed", "1", "-h"]), Ok(None));
+    }
+
+    #[test]
+    fn values_follow_the_flag_or_an_equals_sign() {
+        let expected = Cli {
+            config_dir: Some("configs/hard".to_string()),
+            level: Some("levels/2.level.ron".to_string()),
+            seed: Some(42),
+            window_size: Some((800., 600.)),
+            fullscreen: true,
+            headless: true,
+            max_frames: Some(120),
+            log_level: Some(Level::DEBUG),
+        };
+        let spaced = parse(
+            ALL,
+            &[
+                "--config-dir",
+                "configs/hard",
+                "--level",
+                "levels/2.level.ron",
+                "--seed",
+                "42",
+                "--window-size",
+                "800x600",
+                "--fullscreen",
+                "--headless",
+                "--max-frames",
+                "120",
+                "--log-level",
+                "debug",
+            ],
+        );
+        let joined = parse(
+            ALL,
+            &[
+                "--config-dir=configs/hard",
+                "--level=levels/2.level.ron",
+                "--seed=42",
+                "--window-size=800X600",
+                "--fullscreen",
+                "--headless",
+                "--max-frames=120",
+                "--log-level=debug",
+            ],
+        );
+        assert_eq!(spaced, Ok(Some(expected.clone())));
+        assert_eq!(joined, Ok(Some(expected)));
+        assert_eq!(parse(ALL, &[]), Ok(Some(Cli::default())));
+    }
+
+    #[test]
+    fn bad_values_say_what_was_expected() {
+        assert_eq!(
+            parse(ALL, &["--seed", "abc"]),
+            Err("`--seed` expects a whole number, got `abc`".to_string())
+        );
+        for size in ["0x5", "800", "x600", "800xabc"] {
+            assert_eq!(
+                parse(ALL, &["--window-size", size]),
+                Err(format!(
+                    "`--window-size` expects WIDTHxHEIGHT, got `{}`",
+                    size
+                ))
+            );
+        }
+        assert!(parse(ALL, &["--max-frames=-1"])
+            .unwrap_err()
+            .starts_with("`--max-frames` expects a whole number"));
+        assert!(parse(ALL, &["--log-level", "loud"])
+            .unwrap_err()
+            .starts_with("`--log-level` expects error, warn, info, debug or trace"));
+    }
+
+    #[test]
+    fn switches_take_no_value() {
+        assert_eq!(
+            parse(ALL, &["--fullscreen=1"]),
+            Err("`--fullscreen` takes no value".to_string())
+        );
+        assert_eq!(
+            parse(ALL, &["--headless=true"]),
+            Err("`--headless` takes no value".to_string())
+        );
+    }
+
+    #[test]
+    fn flags_the_binary_does_not_take_are_unknown() {
+        // a2 has no levels
+        let a2 = Flags {
+            level: false,
+            ..ALL
+        };
+        assert_eq!(
+            parse(a2, &["--level", "1.level.ron"]),
+            Err("unknown argument `--level`".to_string())
+        );
+        assert_eq!(
+            parse(Flags::default(), &["--seed=3"]),
+            Err("unknown argument `--seed=3`".to_string())
+        );
+        assert_eq!(
+            parse(ALL, &["level.ron"]),
+            Err("unknown argument `level.ron`".to_string())
+        );
+    }
+
+    #[test]
+    fn a_missing_value_is_an_error() {
+        assert_eq!(
+            parse(ALL, &["--seed"]),
+            Err("`--seed` needs a value".to_string())
+        );
+        assert_eq!(
+            parse(ALL, &["--headless", "--window-size"]),
+            Err("`--window-size` needs a value".to_string())
+        );
+    }
+}
@@ -0,0 +1,5 @@
+//! Code shared between the assignment crates.
+
+pub mod cli;
+pub mod rng;
+pub mod telemetry;
@@ -0,0 +1,178 @@
+// region: includes
+
+use bevy::prelude::*;
+use rand_core::{OsRng, RngCore};
+
+// endregion
+
+// region: Random Distributions
+
+/// Random distributions on top of any [`RngCore`], including `GlobalEntropy`.
+///
+/// Every method only draws from the wrapped generator, so results are reproducible for a
+/// given seed.
+pub trait RandomExt: RngCore {
+    /// Uniform float in `[min, max)`.
+    fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        // 24 random bits fill an f32 mantissa exactly
+        let unit = (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32;
+        min + unit * (max - min)
+    }
+
+    /// Uniform integer in `[min, max]`, the bounds may be given in either order.
+    fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        let (min, max) = (min.min(max), min.max(max));
+        let span = (max - min) as u64 + 1;
+        min + ((self.next_u32() as u64 * span) >> 32) as u32
+    }
+
+    /// Index into `weights` picked with probability proportional to its weight.
+    /// Negative weights count as zero, `None` if no weight is positive.
+    fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().map(|w| w.max(0.)).sum();
+        if total <= 0. {
+            return None;
+        }
+        let mut pick = self.range_f32(0., total);
+        for (i, w) in weights.iter().enumerate() {
+            let w = w.max(0.);
+            if pick < w {
+                return Some(i);
+            }
+            pick -= w;
+        }
+        // float rounding can leave `pick` just past the last positive weight
+        weights.iter().rposition(|w| *w > 0.)
+    }
+
+    /// Picks one of `(item, weight)` with probability proportional to its weight.
+    fn weighted_choice<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+        let weights: Vec<f32> = items.iter().map(|(_, w)| *w).collect();
+        self.weighted_index(&weights).map(|i| &items[i].0)
+    }
+
+    /// Uniform item of `items`, `None` if it is empty.
+    fn choice<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(&items[self.range_u32(0, items.len() as u32 - 1) as usize])
+    }
+
+    /// Vector of length 1 pointing in a uniformly random direction.
+    fn unit_vec2(&mut self) -> Vec2 {
+        Vec2::from_angle(self.range_f32(0., std::f32::consts::TAU))
+    }
+
+    /// Opaque color with every channel uniform in `[min, max)`.
+    fn color_rgb(&mut self, min: f32, max: f32) -> Color {
+        Color::rgb(
+            self.range_f32(min, max),
+            self.range_f32(min, max),
+            self.range_f32(min, max),
+        )
+    }
+
+    /// Uniform color out of `palette`, falling back to [`RandomExt::color_rgb`] in
+    /// `[0.2, 1)` when the palette is empty.
+    fn palette_color(&mut self, palette: &[(f32, f32, f32)]) -> Color {
+        match self.choice(palette) {
+            Some((r, g, b)) => Color::rgb(*r, *g, *b),
+            None => self.color_rgb(0.2, 1.),
+        }
+    }
+}
+
+impl<R: RngCore + ?Sized> RandomExt for R {}
+
+// endregion
+
+// region: Seeding
+
+/// Seed the game's RNG was seeded with, kept so it can be logged or recorded.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RngSeed(pub u64);
+
+/// Picks the RNG seed: `seed`, from the command line or config, or without one a seed drawn
+/// from OS entropy.
+pub fn resolve_seed(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| OsRng.next_u64())
+}
+
+// endregion
+
+#[cfg(test)]
+mod tests {
+    use wyrand::WyRand;
+
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_draws() {
+        let draws = |seed: u64| {
+            let mut rng = WyRand::new(seed);
+            (0..100)
+                .map(|_| {
+                    (
+                        rng.range_f32(-5., 5.),
+                        rng.range_u32(3, 8),
+                        rng.weighted_index(&[1., 0., 2.5, 0.5]),
+                        rng.unit_vec2(),
+                        rng.palette_color(&[(1., 0., 0.), (0., 1., 0.)]),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(42), draws(42));
+        assert_ne!(draws(42), draws(43));
+    }
+
+    #[test]
+    fn draws_stay_in_their_range() {
+        let mut rng = WyRand::new(7);
+        for _ in 0..1000 {
+            let x = rng.range_f32(-1., 2.);
+            assert!((-1. ..2.).contains(&x));
+            assert!((3..=8).contains(&rng.range_u32(3, 8)));
+            assert!((rng.unit_vec2().length() - 1.).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn reversed_bounds_give_the_same_range() {
+        let mut a = WyRand::new(1);
+        let mut b = WyRand::new(1);
+        for _ in 0..100 {
+            assert_eq!(a.range_u32(8, 3), b.range_u32(3, 8));
+        }
+        assert_eq!(a.range_u32(5, 5), 5);
+    }
+
+    #[test]
+    fn weights_without_a_positive_one_pick_nothing() {
+        let mut rng = WyRand::new(0);
+        assert_eq!(rng.weighted_index(&[]), None);
+        assert_eq!(rng.weighted_index(&[0., 0., 0.]), None);
+        assert_eq!(rng.weighted_index(&[-1., 0.]), None);
+        // only the positive weight can be picked
+        for _ in 0..100 {
+            assert_eq!(rng.weighted_index(&[0., -2., 3., 0.]), Some(2));
+        }
+        assert_eq!(rng.choice::<u32>(&[]), None);
+    }
+
+    #[test]
+    fn empty_palette_falls_back_to_a_random_color() {
+        let mut a = WyRand::new(3);
+        let mut b = WyRand::new(3);
+        let color = a.palette_color(&[]);
+        assert_eq!(color, b.color_rgb(0.2, 1.));
+        for channel in [color.r(), color.g(), color.b()] {
+            assert!((0.2..1.).contains(&channel));
+        }
+        assert_eq!(
+            a.palette_color(&[(0.5, 0.25, 1.)]),
+            Color::rgb(0.5, 0.25, 1.)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+// region: includes
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+
+// endregion
+
+// region: Telemetry Structs
+
+#[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TelemetryFormat {
+    /// a header row with the column names, then one line of values per frame
+    Csv,
+    /// one JSON object per frame and line
+    Ndjson,
+}
+
+/// Collision pairs tested and pairs that hit since the last telemetry row. The collision
+/// systems add to it, the telemetry plugin clears it once the row is written.
+#[derive(Resource, Default, Debug)]
+pub struct CollisionCounts {
+    pub checked: u64,
+    pub resolved: u64,
+}
+
+impl CollisionCounts {
+    /// Counts a tested pair, and a hit when `hit`, passing `hit` through.
+    pub fn record(&mut self, hit: bool) -> bool {
+        self.checked += 1;
+        self.resolved += hit as u64;
+        hit
+    }
+}
+
+/// A value in a telemetry row.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Int(u64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Int(value as u64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Int(value as u64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+/// Writes one row per frame to a CSV or NDJSON file. Every row has to have the same columns,
+/// the first one sets them.
+pub struct TelemetryWriter {
+    format: TelemetryFormat,
+    out: BufWriter<File>,
+    columns: Option<Vec<&'static str>>,
+}
+
+// endregion
+
+impl TelemetryWriter {
+    /// Creates (or truncates) the file at `path`, and the directory it is in.
+    pub fn create(path: &str, format: TelemetryFormat) -> Result<Self, String> {
+        if let Some(dir) = Path::new(path).parent() {
+            std::fs::create_dir_all(dir).map_err(|err| format!("{}: {}", path, err))?;
+        }
+        let file = File::create(path).map_err(|err| format!("{}: {}", path, err))?;
+        Ok(Self {
+            format,
+            out: BufWriter::new(file),
+            columns: None,
+        })
+    }
+
+    pub fn write_row(&mut self, row: &[(&'static str, Value)]) -> Result<(), String> {
+        let names: Vec<&'static str> = row.iter().map(|(name, _)| *name).collect();
+        match &self.columns {
+            Some(columns) if *columns != names => {
+                return Err(format!("expected columns {:?}, got {:?}", columns, names));
+            }
+            Some(_) => (),
+            None => {
+                if self.format == TelemetryFormat::Csv {
+                    writeln!(self.out, "{}", names.join(",")).map_err(|err| err.to_string())?;
+                }
+                self.columns = Some(names);
+            }
+        }
+
+        let line = match self.format {
+            TelemetryFormat::Csv => row
+                .iter()
+                .map(|(_, value)| csv_value(value))
+                .collect::<Vec<_>>()
+                .join(","),
+            TelemetryFormat::Ndjson => {
+                let fields: Vec<String> = row
+                    .iter()
+                    .map(|(name, value)| format!("{}:{}", json_string(name), json_value(value)))
+                    .collect();
+                format!("{{{}}}", fields.join(","))
+            }
+        };
+        writeln!(self.out, "{}", line).map_err(|err| err.to_string())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.out.flush().map_err(|err| err.to_string())
+    }
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Int(n) => n.to_string(),
+        Value::Float(x) => x.to_string(),
+        Value::Text(text) if text.contains([',', '"', '\n']) => {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        }
+        Value::Text(text) => text.clone(),
+    }
+}
+
+fn json_value(value: &Value) -> String {
+    match value {
+        Value::Int(n) => n.to_string(),
+        Value::Float(x) if x.is_finite() => x.to_string(),
+        Value::Float(_) => "null".to_string(),
+        Value::Text(text) => json_string(text),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}